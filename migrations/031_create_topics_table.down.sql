@@ -0,0 +1,3 @@
+drop index if exists link_topics_topic;
+drop table if exists link_topics;
+drop table if exists topics;
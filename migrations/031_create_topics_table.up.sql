@@ -0,0 +1,14 @@
+create table if not exists topics (
+  id integer primary key autoincrement,
+  created_at datetime default current_timestamp,
+
+  label text not null
+);
+
+create table if not exists link_topics (
+  link_id integer primary key references links (id) on delete cascade,
+  topic_id integer not null references topics (id) on delete cascade,
+  distance real not null
+);
+
+create index if not exists link_topics_topic on link_topics (topic_id, distance);
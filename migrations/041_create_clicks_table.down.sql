@@ -0,0 +1,2 @@
+drop index if exists clicks_link;
+drop table if exists clicks;
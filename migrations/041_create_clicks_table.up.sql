@@ -0,0 +1,9 @@
+create table if not exists clicks (
+  id integer primary key autoincrement,
+  clicked_at datetime default current_timestamp,
+
+  link_id integer not null references links (id) on delete cascade,
+  query text not null
+);
+
+create index if not exists clicks_link on clicks (link_id, clicked_at);
@@ -0,0 +1,14 @@
+create table if not exists synonyms (
+  id integer primary key autoincrement,
+  created_at datetime default current_timestamp,
+
+  term text not null,
+  synonym text not null,
+
+  unique (term, synonym) on conflict rollback
+);
+
+insert into synonyms (term, synonym)
+values
+('k8s', 'kubernetes'),
+('pr', 'pull request');
@@ -0,0 +1,10 @@
+drop trigger if exists vec_links_updater;
+
+create trigger if not exists vec_links_updater
+after update on links
+begin
+  update vec_links
+  set (vec_source, vec_description)
+  = (lembed(new.source), lembed(new.description))
+  where rowid = old.id;
+end;
@@ -0,0 +1,11 @@
+drop trigger if exists vec_links_updater;
+
+create trigger if not exists vec_links_updater
+after update of source, description on links
+when old.source is not new.source or old.description is not new.description
+begin
+  update vec_links
+  set (vec_source, vec_description)
+  = (lembed(new.source), lembed(new.description))
+  where rowid = old.id;
+end;
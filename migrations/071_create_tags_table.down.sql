@@ -0,0 +1,3 @@
+drop index if exists link_tags_tag;
+drop table if exists link_tags;
+drop table if exists tags;
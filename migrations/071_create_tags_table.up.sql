@@ -0,0 +1,17 @@
+create table if not exists tags (
+  id integer primary key autoincrement,
+  created_at datetime default current_timestamp,
+
+  name text not null,
+
+  unique (name) on conflict rollback
+);
+
+create table if not exists link_tags (
+  link_id integer not null references links (id) on delete cascade,
+  tag_id integer not null references tags (id) on delete cascade,
+
+  primary key (link_id, tag_id)
+);
+
+create index if not exists link_tags_tag on link_tags (tag_id);
@@ -0,0 +1,7 @@
+drop index if exists link_owners_owner;
+drop index if exists links_owner;
+drop table if exists link_owners;
+
+alter table links drop column owner;
+alter table links drop column modified_by;
+alter table links drop column created_by;
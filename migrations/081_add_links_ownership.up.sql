@@ -0,0 +1,13 @@
+alter table links add column created_by text;
+alter table links add column modified_by text;
+alter table links add column owner text;
+
+create table if not exists link_owners (
+  link_id integer not null references links (id) on delete cascade,
+  owner text not null,
+
+  primary key (link_id, owner)
+);
+
+create index if not exists links_owner on links (owner);
+create index if not exists link_owners_owner on link_owners (owner);
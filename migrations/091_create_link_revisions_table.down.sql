@@ -0,0 +1,2 @@
+drop index if exists link_revisions_link;
+drop table if exists link_revisions;
@@ -0,0 +1,27 @@
+create table if not exists link_revisions (
+  id integer primary key autoincrement,
+  revised_at datetime default current_timestamp,
+  revised_by text,
+
+  link_id integer not null,
+  action text not null,
+  before text,
+  after text
+);
+
+create index if not exists link_revisions_link on link_revisions (link_id, id);
+
+insert into link_revisions (revised_at, revised_by, link_id, action, after)
+select created_at, created_by, id, 'Create', json_object(
+  'id', id,
+  'created_by', created_by,
+  'created_at', created_at,
+  'modified_by', modified_by,
+  'modified_at', modified_at,
+  'owner', owner,
+  'source', source,
+  'is_alias', json(case when is_alias then 'true' else 'false' end),
+  'target', target,
+  'description', description
+)
+from links;
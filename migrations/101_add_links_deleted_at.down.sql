@@ -0,0 +1,6 @@
+delete from links where deleted_at is not null;
+
+drop index if exists links_deleted_at;
+
+alter table links drop column deleted_by;
+alter table links drop column deleted_at;
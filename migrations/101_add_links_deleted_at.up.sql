@@ -0,0 +1,4 @@
+alter table links add column deleted_at datetime;
+alter table links add column deleted_by text;
+
+create index if not exists links_deleted_at on links (deleted_at);
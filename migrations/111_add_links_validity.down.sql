@@ -0,0 +1,5 @@
+drop index if exists link_schedules_link;
+drop table if exists link_schedules;
+
+alter table links drop column valid_until;
+alter table links drop column valid_from;
@@ -0,0 +1,13 @@
+alter table links add column valid_from datetime;
+alter table links add column valid_until datetime;
+
+create table if not exists link_schedules (
+  id integer primary key autoincrement,
+  created_at datetime default current_timestamp,
+
+  link_id integer not null references links (id) on delete cascade,
+  target text not null,
+  active_from datetime not null
+);
+
+create index if not exists link_schedules_link on link_schedules (link_id, active_from);
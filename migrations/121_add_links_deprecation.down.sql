@@ -0,0 +1,3 @@
+alter table links drop column deprecation_message;
+alter table links drop column replaced_by;
+alter table links drop column deprecated_at;
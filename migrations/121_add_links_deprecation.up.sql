@@ -0,0 +1,3 @@
+alter table links add column deprecated_at datetime;
+alter table links add column replaced_by text;
+alter table links add column deprecation_message text;
@@ -0,0 +1,3 @@
+drop index if exists links_source_key;
+
+alter table links drop column source_key;
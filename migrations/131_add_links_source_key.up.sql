@@ -0,0 +1,14 @@
+alter table links add column source_key text;
+
+-- An ASCII approximation of source::key, corrected on startup
+update links set source_key = lower(replace(replace(trim(source), '_', '-'), ' ', '-'));
+
+-- Links differing only by case or separators are kept, under a key no lookup will produce,
+-- for their owners to merge or rename
+update links set source_key = source_key || '~' || id
+where exists (
+  select 1 from links as first
+  where first.source_key = links.source_key and first.id < links.id
+);
+
+create unique index if not exists links_source_key on links (source_key);
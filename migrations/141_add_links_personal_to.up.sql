@@ -0,0 +1,71 @@
+-- Rebuilds links without `unique (source)`, as a personal link may share its source with a global
+-- link, leaving uniqueness to links_source_key. Migrations run with foreign keys off (see init_db),
+-- so dropping the old table doesn't cascade to clicks, tags, owners, topics and schedules.
+
+create table links_personal (
+  id integer primary key autoincrement,
+  created_at datetime default current_timestamp,
+  modified_at datetime default current_timestamp,
+
+  source text not null,
+  is_alias boolean not null,
+  target text not null,
+  description text default '',
+
+  created_by text,
+  modified_by text,
+  owner text,
+  deleted_at datetime,
+  deleted_by text,
+  valid_from datetime,
+  valid_until datetime,
+  deprecated_at datetime,
+  replaced_by text,
+  deprecation_message text,
+  source_key text,
+  -- The user a personal link is visible to, or null for a global link
+  personal_to text
+);
+
+insert into links_personal (
+  id, created_at, modified_at, source, is_alias, target, description, created_by, modified_by,
+  owner, deleted_at, deleted_by, valid_from, valid_until, deprecated_at, replaced_by,
+  deprecation_message, source_key
+)
+select
+  id, created_at, modified_at, source, is_alias, target, description, created_by, modified_by,
+  owner, deleted_at, deleted_by, valid_from, valid_until, deprecated_at, replaced_by,
+  deprecation_message, source_key
+from links;
+
+drop table links;
+alter table links_personal rename to links;
+
+create index if not exists links_owner on links (owner);
+create index if not exists links_deleted_at on links (deleted_at);
+-- Each user may shadow a global source with a personal link of the same name
+create unique index if not exists links_source_key on links (source_key, ifnull(personal_to, ''));
+
+create trigger if not exists vec_links_inserter
+after insert on links
+begin
+  insert into vec_links (rowid, vec_source, vec_description)
+  values (new.id, lembed(new.source), lembed(new.description));
+end;
+
+create trigger if not exists vec_links_updater
+after update of source, description on links
+when old.source is not new.source or old.description is not new.description
+begin
+  update vec_links
+  set (vec_source, vec_description)
+  = (lembed(new.source), lembed(new.description))
+  where rowid = old.id;
+end;
+
+create trigger if not exists vec_links_deleter
+after delete on links
+begin
+  delete from vec_links
+  where rowid = old.id;
+end;
@@ -0,0 +1,4 @@
+drop index if exists link_groups_name;
+drop table if exists link_groups;
+
+alter table links drop column visibility;
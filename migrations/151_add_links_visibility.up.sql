@@ -0,0 +1,13 @@
+-- Unlisted links resolve but are hidden from search, restricted links are only visible to their
+-- owners and members of their groups
+alter table links add column visibility text not null default 'public'
+  check (visibility in ('public', 'unlisted', 'restricted'));
+
+create table if not exists link_groups (
+  link_id integer not null references links (id) on delete cascade,
+  name text not null,
+
+  primary key (link_id, name)
+);
+
+create index if not exists link_groups_name on link_groups (name);
@@ -0,0 +1,4 @@
+drop index if exists collection_items_collection;
+drop table if exists collection_items;
+
+alter table links drop column is_collection;
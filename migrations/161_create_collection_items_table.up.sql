@@ -0,0 +1,16 @@
+-- Collections render an ordered page of member links instead of redirecting
+alter table links add column is_collection boolean not null default false;
+
+create table if not exists collection_items (
+  id integer primary key autoincrement,
+  created_at datetime default current_timestamp,
+
+  collection_id integer not null references links (id) on delete cascade,
+  link_id integer not null references links (id) on delete cascade,
+  position integer not null,
+  note text not null default '',
+
+  unique (collection_id, link_id) on conflict rollback
+);
+
+create index if not exists collection_items_collection on collection_items (collection_id, position);
@@ -0,0 +1,5 @@
+drop index if exists clicks_variant;
+alter table clicks drop column variant_id;
+
+drop index if exists link_variants_link;
+drop table if exists link_variants;
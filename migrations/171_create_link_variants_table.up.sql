@@ -0,0 +1,16 @@
+-- Weighted targets that each take a percentage of a link's traffic, the rest going to its own target
+create table if not exists link_variants (
+  id integer primary key autoincrement,
+  created_at datetime default current_timestamp,
+
+  link_id integer not null references links (id) on delete cascade,
+  target text not null,
+  weight integer not null check (weight between 1 and 100)
+);
+
+create index if not exists link_variants_link on link_variants (link_id);
+
+-- The variant served, or null for the link's own target; kept once the variant is removed
+alter table clicks add column variant_id integer;
+
+create index if not exists clicks_variant on clicks (variant_id);
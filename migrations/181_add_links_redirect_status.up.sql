@@ -0,0 +1,3 @@
+-- The HTTP status to redirect with, 303 See Other unless the link asks for another
+alter table links add column redirect_status integer not null default 303
+  check (redirect_status in (301, 302, 303, 307, 308));
@@ -0,0 +1 @@
+alter table links drop column forwarding;
@@ -0,0 +1,3 @@
+-- Whether extra search parameters and fragments are forwarded to the target, and who wins conflicts
+alter table links add column forwarding text not null default 'off'
+  check (forwarding in ('off', 'target', 'request', 'both'));
@@ -0,0 +1,10 @@
+drop trigger if exists links_hits_counter;
+
+drop index if exists links_last_used_at;
+drop index if exists links_hits;
+
+alter table links drop column last_used_at;
+alter table links drop column hits;
+
+alter table clicks drop column user_hash;
+alter table clicks drop column alias_chain;
@@ -0,0 +1,23 @@
+-- The aliases a click was resolved through, as a JSON array ending at the link, and a salted hash
+-- of the user if configured
+alter table clicks add column alias_chain text not null default '[]';
+alter table clicks add column user_hash text;
+
+-- Running totals of clicks, so links can be sorted by use
+alter table links add column hits integer not null default 0;
+alter table links add column last_used_at datetime;
+
+update links set
+  hits = (select count(*) from clicks where clicks.link_id = links.id),
+  last_used_at = (select max(clicked_at) from clicks where clicks.link_id = links.id);
+
+create index if not exists links_hits on links (hits);
+create index if not exists links_last_used_at on links (last_used_at);
+
+create trigger if not exists links_hits_counter
+after insert on clicks
+begin
+  update links
+  set hits = hits + 1, last_used_at = max(coalesce(last_used_at, ''), new.clicked_at)
+  where id = new.link_id;
+end;
@@ -0,0 +1,6 @@
+-- Values the service keeps for itself, e.g. which revision of source::key the stored source keys
+-- were computed with
+create table if not exists settings (
+  name text primary key not null,
+  value text not null
+);
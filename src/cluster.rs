@@ -0,0 +1,222 @@
+const MAX_ITERATIONS: usize = 50;
+const LABEL_SOURCES: usize = 3;
+
+pub struct Embedding {
+    pub id: i64,
+    pub vector: Vec<f32>,
+}
+impl Embedding {
+    pub fn from_blob(id: i64, blob: &[u8]) -> Self {
+        let vector = blob
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        Self { id, vector }
+    }
+}
+
+pub struct Member {
+    pub id: i64,
+    pub distance: f32,
+}
+
+pub struct Cluster {
+    /// Members ordered from most to least central
+    pub members: Vec<Member>,
+}
+impl Cluster {
+    pub fn central(&self) -> impl Iterator<Item = i64> + '_ {
+        self.members
+            .iter()
+            .take(LABEL_SOURCES)
+            .map(|member| member.id)
+    }
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+fn nearest(vector: &[f32], centroids: &[Vec<f32>]) -> (usize, f32) {
+    centroids
+        .iter()
+        .map(|centroid| distance(vector, centroid))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap_or((0, f32::MAX))
+}
+
+fn mean<'a>(vectors: impl Iterator<Item = &'a Vec<f32>>, dims: usize) -> Option<Vec<f32>> {
+    let mut sum = vec![0.0; dims];
+    let mut count = 0;
+    for vector in vectors {
+        sum.iter_mut().zip(vector).for_each(|(s, v)| *s += v);
+        count += 1;
+    }
+    (count > 0).then(|| sum.into_iter().map(|s| s / count as f32).collect())
+}
+
+/// Deterministic farthest-point seeding, starting from the embedding nearest the global mean
+fn seed(embeddings: &[Embedding], k: usize, dims: usize) -> Vec<Vec<f32>> {
+    let global = mean(embeddings.iter().map(|e| &e.vector), dims).unwrap_or_default();
+    let first = embeddings
+        .iter()
+        .map(|e| distance(&e.vector, &global))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+        .unwrap_or_default();
+
+    let mut centroids = vec![embeddings[first].vector.clone()];
+    while centroids.len() < k {
+        let farthest = embeddings
+            .iter()
+            .map(|e| nearest(&e.vector, &centroids).1)
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+            .unwrap_or_default();
+        centroids.push(embeddings[farthest].vector.clone());
+    }
+    centroids
+}
+
+/// Default cluster count for `n` embeddings, the rule-of-thumb `sqrt(n / 2)`
+pub fn default_k(n: usize) -> usize {
+    ((n as f64 / 2.0).sqrt().ceil() as usize).max(1)
+}
+
+/// Group embeddings into at most `k` clusters using k-means
+pub fn kmeans(embeddings: &[Embedding], k: usize) -> Vec<Cluster> {
+    let k = k.min(embeddings.len());
+    if k == 0 {
+        return vec![];
+    }
+    let dims = embeddings[0].vector.len();
+
+    let mut centroids = seed(embeddings, k, dims);
+    let mut assignments = vec![usize::MAX; embeddings.len()];
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (embedding, assignment) in embeddings.iter().zip(assignments.iter_mut()) {
+            let (cluster, _) = nearest(&embedding.vector, &centroids);
+            if *assignment != cluster {
+                *assignment = cluster;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let members = embeddings
+                .iter()
+                .zip(&assignments)
+                .filter(|&(_, &assignment)| assignment == cluster)
+                .map(|(e, _)| &e.vector);
+            if let Some(updated) = mean(members, dims) {
+                *centroid = updated;
+            }
+        }
+    }
+
+    let mut clusters = centroids
+        .iter()
+        .map(|_| Cluster { members: vec![] })
+        .collect::<Vec<_>>();
+    for (embedding, &assignment) in embeddings.iter().zip(&assignments) {
+        clusters[assignment].members.push(Member {
+            id: embedding.id,
+            distance: distance(&embedding.vector, &centroids[assignment]),
+        });
+    }
+    clusters.retain(|cluster| !cluster.members.is_empty());
+    for cluster in clusters.iter_mut() {
+        cluster
+            .members
+            .sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    }
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embeddings(vectors: &[[f32; 2]]) -> Vec<Embedding> {
+        vectors
+            .iter()
+            .enumerate()
+            .map(|(id, vector)| Embedding {
+                id: id as i64,
+                vector: vector.to_vec(),
+            })
+            .collect()
+    }
+
+    fn ids(cluster: &Cluster) -> Vec<i64> {
+        let mut ids = cluster
+            .members
+            .iter()
+            .map(|member| member.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn kmeans_separates_groups() {
+        let embeddings = embeddings(&[
+            [0.0, 0.0],
+            [10.0, 10.0],
+            [0.2, 0.1],
+            [10.1, 9.8],
+            [0.1, 0.3],
+            [9.9, 10.2],
+        ]);
+        let mut clusters = kmeans(&embeddings, 2);
+        clusters.sort_by_key(|cluster| ids(cluster)[0]);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(ids(&clusters[0]), vec![0, 2, 4]);
+        assert_eq!(ids(&clusters[1]), vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn kmeans_orders_members_by_centrality() {
+        let embeddings = embeddings(&[[0.0, 0.0], [4.0, 0.0], [1.0, 0.0], [2.0, 0.0]]);
+        let clusters = kmeans(&embeddings, 1);
+        assert_eq!(clusters.len(), 1);
+        let distances = clusters[0]
+            .members
+            .iter()
+            .map(|member| member.distance)
+            .collect::<Vec<_>>();
+        assert!(distances.is_sorted());
+        assert_eq!(clusters[0].central().collect::<Vec<_>>(), vec![3, 2, 0]);
+    }
+
+    #[test]
+    fn kmeans_caps_clusters_at_embeddings() {
+        let embeddings = embeddings(&[[0.0, 0.0], [1.0, 1.0]]);
+        assert_eq!(kmeans(&embeddings, 5).len(), 2);
+        assert!(kmeans(&embeddings, 0).is_empty());
+        assert!(kmeans(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn embeddings_decode_little_endian_floats() {
+        let blob = [1.5f32, -2.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(Embedding::from_blob(7, &blob).vector, vec![1.5, -2.0]);
+    }
+
+    #[test]
+    fn default_k_grows_slowly() {
+        assert_eq!(default_k(0), 1);
+        assert_eq!(default_k(2), 1);
+        assert_eq!(default_k(200), 10);
+    }
+}
@@ -0,0 +1,172 @@
+use std::{fs, io};
+
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    schema::{PagingOptions, QueryLinks, SearchMethod, SearchOptions},
+    service::query_links,
+};
+
+/// A labelled query, one per line of the evaluation file, e.g.
+/// `{"query": "web search", "expected": ["google", "bing"]}`
+#[derive(Debug, Deserialize)]
+pub struct Judgement {
+    pub query: String,
+    pub expected: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub queries: usize,
+    pub recall: f64,
+    pub mrr: f64,
+    pub ndcg: f64,
+}
+impl Metrics {
+    fn add(&mut self, expected: &[String], results: &[String], k: usize) {
+        let relevant = results
+            .iter()
+            .map(|source| expected.contains(source))
+            .collect::<Vec<_>>();
+        let hits = relevant.iter().filter(|&&rel| rel).count();
+
+        let dcg = relevant
+            .iter()
+            .enumerate()
+            .filter(|&(_, &rel)| rel)
+            .map(|(i, _)| 1.0 / (i as f64 + 2.0).log2())
+            .sum::<f64>();
+        let idcg = (0..expected.len().min(k))
+            .map(|i| 1.0 / (i as f64 + 2.0).log2())
+            .sum::<f64>();
+
+        self.queries += 1;
+        // No more than `k` expected sources can be found in `k` results
+        if !expected.is_empty() {
+            self.recall += hits as f64 / expected.len().min(k) as f64;
+        }
+        if let Some(rank) = relevant.iter().position(|&rel| rel) {
+            self.mrr += 1.0 / (rank as f64 + 1.0);
+        }
+        if idcg > 0.0 {
+            self.ndcg += dcg / idcg;
+        }
+    }
+
+    fn mean(self) -> Self {
+        let n = self.queries.max(1) as f64;
+        Self {
+            queries: self.queries,
+            recall: self.recall / n,
+            mrr: self.mrr / n,
+            ndcg: self.ndcg / n,
+        }
+    }
+}
+
+pub fn read_judgements(path: &str) -> io::Result<Vec<Judgement>> {
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(io::Error::other))
+        .collect()
+}
+
+pub async fn evaluate(
+    app_state: &AppState,
+    judgements: &[Judgement],
+    method: SearchMethod,
+    boost: bool,
+    k: u64,
+) -> Result<Metrics, sqlx::Error> {
+    let mut metrics = Metrics::default();
+    for Judgement { query, expected } in judgements {
+        let query = QueryLinks {
+            paging: PagingOptions { page: 1, limit: k },
+            search: SearchOptions {
+                query: query.clone(),
+                method,
+                boost,
+            },
+            ..Default::default()
+        };
+        let (links, _) = query_links(app_state, &query).await?;
+        let results = links
+            .into_iter()
+            .map(|link| link.source)
+            .collect::<Vec<_>>();
+        metrics.add(expected, &results, k as usize);
+    }
+
+    Ok(metrics.mean())
+}
+
+/// Run every `SearchMethod`, with and without boosting, over the labelled queries and print a
+/// comparison table
+pub async fn report(app_state: &AppState, path: &str, k: u64) -> Result<(), sqlx::Error> {
+    let judgements = read_judgements(path)?;
+    println!(
+        "📏 Evaluating {} queries from '{path}' at k={k}",
+        judgements.len()
+    );
+
+    let mut rows = vec![];
+    for method in SearchMethod::ALL {
+        for boost in [false, true] {
+            let metrics = evaluate(app_state, &judgements, method, boost, k).await?;
+            rows.push((method, boost, metrics));
+        }
+    }
+
+    println!(
+        "{:<20} {:>6} {:>8} {:>10} {:>8} {:>10}",
+        "method", "boost", "queries", "recall@k", "mrr", "ndcg@k"
+    );
+    for (method, boost, metrics) in rows {
+        println!(
+            "{:<20} {:>6} {:>8} {:>10.4} {:>8.4} {:>10.4}",
+            method.to_string(),
+            boost,
+            metrics.queries,
+            metrics.recall,
+            metrics.mrr,
+            metrics.ndcg
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(sources: &[&str]) -> Vec<String> {
+        sources.iter().map(|source| source.to_string()).collect()
+    }
+
+    #[test]
+    fn recall_is_out_of_what_fits_in_k() {
+        let expected = sources(&["google", "bing", "duckduckgo", "yahoo"]);
+        let mut metrics = Metrics::default();
+        metrics.add(&expected, &sources(&["bing", "google"]), 2);
+        let metrics = metrics.mean();
+        assert_eq!(metrics.recall, 1.0);
+        assert_eq!(metrics.mrr, 1.0);
+        assert!((metrics.ndcg - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn misses_rank_lower() {
+        let expected = sources(&["google"]);
+        let mut metrics = Metrics::default();
+        metrics.add(&expected, &sources(&["bing", "google", "yahoo"]), 3);
+        metrics.add(&expected, &sources(&["bing", "yahoo"]), 3);
+        let metrics = metrics.mean();
+        assert_eq!(metrics.queries, 2);
+        assert_eq!(metrics.recall, 0.5);
+        assert_eq!(metrics.mrr, 0.25);
+        assert!((metrics.ndcg - 0.5 / 3f64.log2()).abs() < 1e-9);
+    }
+}
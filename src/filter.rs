@@ -0,0 +1,423 @@
+use chrono::NaiveDate;
+
+use crate::{identity::Identity, source};
+
+/// Host part of `links.target`, lowercased, e.g. `confluence.example.com`
+const HOST: &str = r#"lower(substr(
+    substr(links.target, instr(links.target, '://') + 3),
+    1,
+    case
+        when instr(substr(links.target, instr(links.target, '://') + 3), '/') > 0
+        then instr(substr(links.target, instr(links.target, '://') + 3), '/') - 1
+        else length(links.target)
+    end
+))"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Source,
+    Target,
+    Domain,
+    Description,
+}
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "source" => Some(Self::Source),
+            "target" => Some(Self::Target),
+            "domain" => Some(Self::Domain),
+            "desc" | "description" => Some(Self::Description),
+            _ => None,
+        }
+    }
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Source => "lower(links.source)",
+            Self::Target => "lower(links.target)",
+            Self::Domain => HOST,
+            Self::Description => "lower(links.description)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamp {
+    Created,
+    Modified,
+}
+impl Timestamp {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "created" => Some(Self::Created),
+            "modified" => Some(Self::Modified),
+            _ => None,
+        }
+    }
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Created => "date(links.created_at)",
+            Self::Modified => "date(links.modified_at)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Before,
+    OnOrBefore,
+    On,
+    OnOrAfter,
+    After,
+}
+impl Comparison {
+    fn parse(value: &str) -> (Self, &str) {
+        [
+            (">=", Self::OnOrAfter),
+            ("<=", Self::OnOrBefore),
+            (">", Self::After),
+            ("<", Self::Before),
+            ("=", Self::On),
+        ]
+        .into_iter()
+        .find_map(|(prefix, comparison)| {
+            value.strip_prefix(prefix).map(|value| (comparison, value))
+        })
+        .unwrap_or((Self::On, value))
+    }
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Before => "<",
+            Self::OnOrBefore => "<=",
+            Self::On => "=",
+            Self::OnOrAfter => ">=",
+            Self::After => ">",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Owner {
+    /// Whoever is searching, resolved when compiled
+    Me,
+    Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    /// Free text, used for ranking
+    Text(String),
+    /// A quoted phrase, used for ranking and required to appear in the source or description
+    Phrase(String),
+    Contains(Field, String),
+    IsAlias,
+    Tagged(String),
+    /// Owned or co-owned by
+    Owned(Owner),
+    /// Within a namespace or any namespace below it, as a source key
+    Namespaced(String),
+    Dated(Timestamp, Comparison, NaiveDate),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clause {
+    pub negated: bool,
+    pub term: Term,
+}
+
+/// A parsed search query, e.g. `wiki domain:confluence is:alias tag:infra ns:team -"old space"`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    /// Free text and phrases to rank by
+    pub text: String,
+    pub clauses: Vec<Clause>,
+}
+
+/// Split on whitespace, keeping quoted phrases (including `field:"quoted value"`) together
+fn tokenize(query: &str) -> Vec<(String, bool)> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                was_quoted = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push((std::mem::take(&mut token), was_quoted));
+                }
+                was_quoted = false;
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push((token, was_quoted));
+    }
+    tokens
+}
+
+fn parse_term(token: &str, quoted: bool) -> Term {
+    if let Some((name, value)) = token.split_once(':')
+        && !value.is_empty()
+    {
+        if let Some(field) = Field::parse(name) {
+            return Term::Contains(field, value.to_lowercase());
+        }
+        if let Some(timestamp) = Timestamp::parse(name) {
+            let (comparison, date) = Comparison::parse(value);
+            if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                return Term::Dated(timestamp, comparison, date);
+            }
+        }
+        if name == "is" && value == "alias" {
+            return Term::IsAlias;
+        }
+        if name == "tag" {
+            return Term::Tagged(value.to_lowercase());
+        }
+        if matches!(name, "ns" | "namespace") {
+            return Term::Namespaced(source::key(value.trim_matches('/')));
+        }
+        if name == "owner" {
+            return Term::Owned(match value {
+                "me" => Owner::Me,
+                _ => Owner::Named(value.to_string()),
+            });
+        }
+    }
+    if quoted {
+        Term::Phrase(token.to_lowercase())
+    } else {
+        Term::Text(token.to_string())
+    }
+}
+
+impl Filter {
+    pub fn parse(query: &str) -> Self {
+        let mut text = vec![];
+        let mut clauses = vec![];
+        for (token, quoted) in tokenize(query) {
+            let (negated, token) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, token.as_str()),
+            };
+            let term = parse_term(token, quoted);
+            match &term {
+                Term::Text(word) if !negated => {
+                    text.push(word.clone());
+                    continue;
+                }
+                Term::Phrase(phrase) if !negated => text.push(phrase.clone()),
+                _ => {}
+            }
+            clauses.push(Clause { negated, term });
+        }
+        Self {
+            text: text.join(" "),
+            clauses,
+        }
+    }
+
+    /// Conditions on `links` to be and-ed into a where clause, with their text bindings in order
+    pub fn as_sql(&self, identity: &Identity) -> (String, Vec<String>) {
+        let mut conditions = vec![];
+        let mut binds = vec![];
+        for Clause { negated, term } in &self.clauses {
+            let condition = match term {
+                Term::Text(value) | Term::Phrase(value) => {
+                    binds.push(value.to_lowercase());
+                    binds.push(value.to_lowercase());
+                    "(instr(lower(links.source), ?) > 0 or instr(lower(links.description), ?) > 0)"
+                        .to_string()
+                }
+                Term::Contains(field, value) => {
+                    binds.push(value.clone());
+                    format!("instr({}, ?) > 0", field.as_sql())
+                }
+                Term::IsAlias => "links.is_alias".to_string(),
+                Term::Tagged(name) => {
+                    binds.push(name.clone());
+                    r#"exists (
+                        select 1 from link_tags
+                        join tags on tags.id = link_tags.tag_id
+                        where link_tags.link_id = links.id and tags.name = ?
+                    )"#
+                    .to_string()
+                }
+                Term::Owned(owner) => {
+                    let owner = match owner {
+                        Owner::Me => identity.user.clone(),
+                        Owner::Named(name) => Some(name.clone()),
+                    };
+                    if let Some(owner) = owner {
+                        binds.push(owner.clone());
+                        binds.push(owner);
+                        r#"(links.owner = ? or exists (
+                            select 1 from link_owners
+                            where link_owners.link_id = links.id and link_owners.owner = ?
+                        ))"#
+                        .to_string()
+                    } else {
+                        "false".to_string()
+                    }
+                }
+                Term::Namespaced(namespace) => {
+                    let prefix = format!("{namespace}/");
+                    binds.push(prefix.clone());
+                    binds.push(prefix);
+                    "substr(links.source_key, 1, length(?)) = ?".to_string()
+                }
+                Term::Dated(timestamp, comparison, date) => {
+                    binds.push(date.format("%Y-%m-%d").to_string());
+                    format!("{} {} ?", timestamp.as_sql(), comparison.as_sql())
+                }
+            };
+            if *negated {
+                conditions.push(format!("not ({condition})"));
+            } else {
+                conditions.push(condition);
+            }
+        }
+        if conditions.is_empty() {
+            ("true".to_string(), binds)
+        } else {
+            (conditions.join(" and "), binds)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clause(term: Term) -> Clause {
+        Clause {
+            negated: false,
+            term,
+        }
+    }
+
+    #[test]
+    fn quoted_values_stay_together() {
+        let filter = Filter::parse(r#"wiki domain:"Confluence Example" -"old space""#);
+        assert_eq!(filter.text, "wiki");
+        assert_eq!(
+            filter.clauses,
+            vec![
+                clause(Term::Contains(
+                    Field::Domain,
+                    "confluence example".to_string()
+                )),
+                Clause {
+                    negated: true,
+                    term: Term::Phrase("old space".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_phrases_rank_and_filter() {
+        let filter = Filter::parse(r#""On Call" rota"#);
+        assert_eq!(filter.text, "on call rota");
+        assert_eq!(
+            filter.clauses,
+            vec![clause(Term::Phrase("on call".to_string()))]
+        );
+    }
+
+    #[test]
+    fn unknown_prefixes_are_text() {
+        let filter = Filter::parse("colour:blue is:pinned created:yesterday");
+        assert_eq!(filter.text, "colour:blue is:pinned created:yesterday");
+        assert!(filter.clauses.is_empty());
+    }
+
+    #[test]
+    fn repeated_keys_all_apply() {
+        let filter = Filter::parse("tag:Infra tag:oncall -tag:old");
+        assert_eq!(filter.text, "");
+        assert_eq!(
+            filter.clauses,
+            vec![
+                clause(Term::Tagged("infra".to_string())),
+                clause(Term::Tagged("oncall".to_string())),
+                Clause {
+                    negated: true,
+                    term: Term::Tagged("old".to_string()),
+                },
+            ]
+        );
+        let (sql, binds) = filter.as_sql(&Identity::default());
+        assert_eq!(sql.matches("exists").count(), 3);
+        assert_eq!(binds, vec!["infra", "oncall", "old"]);
+    }
+
+    #[test]
+    fn empty_values_are_text() {
+        let filter = Filter::parse("tag: owner: - wiki");
+        assert_eq!(filter.text, "tag: owner: - wiki");
+        assert!(filter.clauses.is_empty());
+        assert_eq!(filter.as_sql(&Identity::default()).0, "true");
+    }
+
+    #[test]
+    fn dates_compare() {
+        let filter = Filter::parse("created:>=2026-01-31 modified:2026-02-01");
+        assert_eq!(
+            filter.clauses,
+            vec![
+                clause(Term::Dated(
+                    Timestamp::Created,
+                    Comparison::OnOrAfter,
+                    NaiveDate::from_ymd_opt(2026, 1, 31).unwrap()
+                )),
+                clause(Term::Dated(
+                    Timestamp::Modified,
+                    Comparison::On,
+                    NaiveDate::from_ymd_opt(2026, 2, 1).unwrap()
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn owner_me_is_whoever_is_searching() {
+        let filter = Filter::parse("owner:me");
+        assert_eq!(filter.clauses, vec![clause(Term::Owned(Owner::Me))]);
+
+        let identity = Identity {
+            user: Some("ada".to_string()),
+            ..Default::default()
+        };
+        let (sql, binds) = filter.as_sql(&identity);
+        assert!(sql.contains("links.owner = ?"));
+        assert_eq!(binds, vec!["ada", "ada"]);
+
+        let (sql, binds) = filter.as_sql(&Identity::default());
+        assert_eq!(sql, "false");
+        assert!(binds.is_empty());
+    }
+
+    #[test]
+    fn owner_named_is_kept_as_given() {
+        let filter = Filter::parse("-owner:Grace");
+        let (sql, binds) = filter.as_sql(&Identity::default());
+        assert!(sql.starts_with("not ("));
+        assert_eq!(binds, vec!["Grace", "Grace"]);
+    }
+
+    #[test]
+    fn namespaces_match_below_their_key() {
+        let filter = Filter::parse("ns:/Team/");
+        assert_eq!(
+            filter.clauses,
+            vec![clause(Term::Namespaced(source::key("team")))]
+        );
+        let (_, binds) = filter.as_sql(&Identity::default());
+        assert_eq!(binds, vec!["team/", "team/"]);
+    }
+}
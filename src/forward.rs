@@ -0,0 +1,374 @@
+use url::{Url, form_urlencoded};
+
+use crate::model::{Forwarding, Link, Variant};
+
+/// Parameters `/search` uses itself, which are never forwarded
+const SEARCH_PARAMS: [&str; 4] = ["query", "method", "boost", "preview"];
+
+/// What a request brings along for a link's target, e.g. `from=now-1h` in
+/// `/search?query=grafana&from=now-1h`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Passthrough {
+    pub params: Vec<(String, String)>,
+    pub fragment: Option<String>,
+}
+impl Passthrough {
+    /// The parameters of a raw `/search` query string that aren't its own
+    pub fn from_query(raw: &str) -> Self {
+        let params = form_urlencoded::parse(raw.as_bytes())
+            .into_owned()
+            .filter(|(name, _)| !SEARCH_PARAMS.contains(&name.as_str()))
+            .collect();
+
+        Self {
+            params,
+            fragment: None,
+        }
+    }
+
+    /// Split a fragment off a searched source, as browsers send `go/wiki#setup` from keyword
+    /// searches, returning the source alone
+    pub fn split_fragment<'q>(&mut self, query: &'q str) -> &'q str {
+        match query.split_once('#') {
+            Some((source, fragment)) => {
+                self.fragment = Some(fragment.to_string()).filter(|fragment| !fragment.is_empty());
+                source
+            }
+            None => query,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty() && self.fragment.is_none()
+    }
+}
+
+/// Point a resolved link at the variant a visitor is sticky to, if any, and forward what the
+/// request brings along into its target, returning the variant picked
+pub fn route(link: &mut Link, visitor: &str, passthrough: &Passthrough) -> Option<Variant> {
+    let variant = link.pick_variant(visitor).cloned();
+    if let Some(variant) = &variant {
+        link.target = variant.target.clone();
+    }
+    link.target = forward(link, passthrough);
+    variant
+}
+
+/// The target to redirect a resolved link to, with whatever the request brings along merged in
+/// according to the link's forwarding rule
+pub fn forward(link: &Link, passthrough: &Passthrough) -> String {
+    if link.forwarding == Forwarding::Off || passthrough.is_empty() {
+        return link.target.clone();
+    }
+    let Ok(mut url) = Url::parse(&link.target) else {
+        return link.target.clone();
+    };
+
+    if !passthrough.params.is_empty() {
+        let own = url.query_pairs().into_owned().collect::<Vec<_>>();
+        let has = |params: &[(String, String)], name: &str| params.iter().any(|(n, _)| n == name);
+        let merged = match link.forwarding {
+            Forwarding::Off => own,
+            Forwarding::Target => own
+                .iter()
+                .chain(
+                    passthrough
+                        .params
+                        .iter()
+                        .filter(|(name, _)| !has(&own, name)),
+                )
+                .cloned()
+                .collect(),
+            Forwarding::Request => own
+                .iter()
+                .filter(|(name, _)| !has(&passthrough.params, name))
+                .chain(passthrough.params.iter())
+                .cloned()
+                .collect(),
+            Forwarding::Both => own
+                .iter()
+                .chain(passthrough.params.iter())
+                .cloned()
+                .collect(),
+        };
+        url.query_pairs_mut().clear().extend_pairs(merged);
+    }
+
+    if let Some(fragment) = &passthrough.fragment
+        && (url.fragment().is_none() || link.forwarding != Forwarding::Target)
+    {
+        url.set_fragment(Some(fragment));
+    }
+
+    url.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        identity::Identity,
+        service::{create_link, resolve_alias},
+    };
+
+    /// A link as `resolve_alias` hands it back, with its active target in `target`
+    fn resolved(target: &str, forwarding: Forwarding) -> Link {
+        serde_json::from_value(json!({
+            "id": 1,
+            "created_by": null,
+            "created_at": "2026-01-01T00:00:00",
+            "modified_by": null,
+            "modified_at": "2026-01-01T00:00:00",
+            "owner": null,
+            "source": "grafana",
+            "is_alias": false,
+            "target": target,
+            "forwarding": forwarding,
+            "description": "",
+        }))
+        .unwrap()
+    }
+
+    fn passthrough(raw: &str, query: &str) -> Passthrough {
+        let mut passthrough = Passthrough::from_query(raw);
+        passthrough.split_fragment(query);
+        passthrough
+    }
+
+    /// What `find_link_handler` makes of a raw `/search` query string: what to bring along,
+    /// and the source to resolve
+    fn request(raw: &str) -> (Passthrough, String) {
+        let query = form_urlencoded::parse(raw.as_bytes())
+            .find(|(name, _)| name == "query")
+            .map(|(_, query)| query.into_owned())
+            .unwrap_or_default();
+        let mut passthrough = Passthrough::from_query(raw);
+        let source = passthrough.split_fragment(&query).to_string();
+        (passthrough, source)
+    }
+
+    fn variant(id: i64, target: &str, weight: i64) -> Variant {
+        serde_json::from_value(json!({
+            "id": id,
+            "created_at": "2026-01-01T00:00:00",
+            "link_id": 1,
+            "target": target,
+            "weight": weight,
+            "clicks": 0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn fragment_in_the_query_is_routed() {
+        let (passthrough, source) = request("query=wiki%23setup&method=Semantic&os=linux");
+        assert_eq!(source, "wiki");
+
+        let mut link = resolved("https://wiki.example.com/start", Forwarding::Request);
+        assert_eq!(route(&mut link, "visitor", &passthrough), None);
+        assert_eq!(link.target, "https://wiki.example.com/start?os=linux#setup");
+    }
+
+    #[test]
+    fn alias_chain_routes_into_its_destination() {
+        let (passthrough, source) = request("query=docs%23faq&lang=en");
+        assert_eq!(source, "docs");
+
+        // `docs` -> `handbook` -> the destination, whose rule applies
+        let mut link = resolved("https://handbook.example.com/?lang=de", Forwarding::Target);
+        link.via = vec!["docs".to_string(), "handbook".to_string()];
+        route(&mut link, "visitor", &passthrough);
+        assert_eq!(link.target, "https://handbook.example.com/?lang=de#faq");
+    }
+
+    #[tokio::test]
+    async fn stored_alias_chain_routes_into_its_destination() {
+        let Some(app_state) = crate::test_state().await else {
+            return;
+        };
+        let identity = Identity::default();
+        for (source, is_alias, target, forwarding) in [
+            (
+                "handbook",
+                false,
+                "https://handbook.example.com/?lang=de",
+                Forwarding::Target,
+            ),
+            ("docs", true, "handbook", Forwarding::Off),
+            ("manual", true, "docs", Forwarding::Off),
+        ] {
+            let create = serde_json::from_value(json!({
+                "source": source,
+                "is_alias": is_alias,
+                "target": target,
+                "forwarding": forwarding,
+            }))
+            .unwrap();
+            create_link(&app_state, &identity, &create).await.unwrap();
+        }
+
+        let (passthrough, source) = request("query=manual%23faq&lang=en&from=now-1h");
+        let mut link = resolve_alias(app_state, &identity, source)
+            .await
+            .unwrap()
+            .expect("the chain resolves");
+        assert_eq!(link.via, vec!["manual", "docs"]);
+
+        // The destination's rule applies, not the aliases'
+        assert_eq!(route(&mut link, "visitor", &passthrough), None);
+        assert_eq!(
+            link.target,
+            "https://handbook.example.com/?lang=de&from=now-1h#faq"
+        );
+    }
+
+    #[test]
+    fn variant_target_is_routed_into() {
+        let (passthrough, source) = request("query=grafana&from=now-1h");
+        assert_eq!(source, "grafana");
+
+        let mut link = resolved("https://grafana.example.com/d/abc", Forwarding::Target);
+        link.variants = vec![variant(7, "https://grafana.example.com/d/new?orgId=1", 100)];
+        let picked = route(&mut link, "visitor", &passthrough);
+        assert_eq!(picked.map(|variant| variant.id), Some(7));
+        assert_eq!(
+            link.target,
+            "https://grafana.example.com/d/new?orgId=1&from=now-1h"
+        );
+    }
+
+    #[test]
+    fn search_params_are_not_forwarded() {
+        let passthrough = passthrough(
+            "query=grafana&method=DamerauLevenshtein&boost=true&from=now-1h",
+            "",
+        );
+        assert_eq!(
+            passthrough.params,
+            vec![("from".to_string(), "now-1h".to_string())]
+        );
+    }
+
+    #[test]
+    fn off_drops_params_and_fragment() {
+        let link = resolved("https://grafana.example.com/d/abc", Forwarding::Off);
+        let passthrough = passthrough("query=grafana&from=now-1h", "grafana#panel-2");
+        assert_eq!(
+            forward(&link, &passthrough),
+            "https://grafana.example.com/d/abc"
+        );
+    }
+
+    #[test]
+    fn nothing_to_forward_keeps_target_as_is() {
+        let link = resolved("https://example.com/a?b=c%20d", Forwarding::Request);
+        assert_eq!(
+            forward(&link, &Passthrough::default()),
+            "https://example.com/a?b=c%20d"
+        );
+    }
+
+    #[test]
+    fn params_are_appended_to_target() {
+        let link = resolved("https://grafana.example.com/d/abc", Forwarding::Target);
+        let passthrough = passthrough("query=grafana&from=now-1h&to=now", "grafana");
+        assert_eq!(
+            forward(&link, &passthrough),
+            "https://grafana.example.com/d/abc?from=now-1h&to=now"
+        );
+    }
+
+    #[test]
+    fn target_wins_conflicts() {
+        let link = resolved(
+            "https://grafana.example.com/d/abc?orgId=1&from=now-6h",
+            Forwarding::Target,
+        );
+        let passthrough = passthrough("query=grafana&from=now-1h&var=x", "grafana");
+        assert_eq!(
+            forward(&link, &passthrough),
+            "https://grafana.example.com/d/abc?orgId=1&from=now-6h&var=x"
+        );
+    }
+
+    #[test]
+    fn request_wins_conflicts() {
+        let link = resolved(
+            "https://grafana.example.com/d/abc?orgId=1&from=now-6h",
+            Forwarding::Request,
+        );
+        let passthrough = passthrough("query=grafana&from=now-1h&var=x", "grafana");
+        assert_eq!(
+            forward(&link, &passthrough),
+            "https://grafana.example.com/d/abc?orgId=1&from=now-1h&var=x"
+        );
+    }
+
+    #[test]
+    fn both_keeps_conflicting_values() {
+        let link = resolved("https://example.com/search?tag=a", Forwarding::Both);
+        let passthrough = passthrough("query=tags&tag=b", "tags");
+        assert_eq!(
+            forward(&link, &passthrough),
+            "https://example.com/search?tag=a&tag=b"
+        );
+    }
+
+    #[test]
+    fn target_fragment_is_preserved() {
+        let link = resolved("https://wiki.example.com/setup#linux", Forwarding::Request);
+        let passthrough = passthrough("query=setup&os=linux", "setup");
+        assert_eq!(
+            forward(&link, &passthrough),
+            "https://wiki.example.com/setup?os=linux#linux"
+        );
+    }
+
+    #[test]
+    fn request_fragment_is_forwarded() {
+        let link = resolved("https://wiki.example.com/setup", Forwarding::Target);
+        let passthrough = passthrough("query=setup%23macos", "setup#macos");
+        assert_eq!(
+            forward(&link, &passthrough),
+            "https://wiki.example.com/setup#macos"
+        );
+    }
+
+    #[test]
+    fn fragment_conflicts_follow_the_rule() {
+        let target = "https://wiki.example.com/setup#linux";
+        let passthrough = passthrough("", "setup#macos");
+        assert_eq!(
+            forward(&resolved(target, Forwarding::Target), &passthrough),
+            "https://wiki.example.com/setup#linux"
+        );
+        assert_eq!(
+            forward(&resolved(target, Forwarding::Request), &passthrough),
+            "https://wiki.example.com/setup#macos"
+        );
+        assert_eq!(
+            forward(&resolved(target, Forwarding::Both), &passthrough),
+            "https://wiki.example.com/setup#macos"
+        );
+    }
+
+    #[test]
+    fn split_fragment_returns_source() {
+        let mut passthrough = Passthrough::default();
+        assert_eq!(passthrough.split_fragment("setup#macos"), "setup");
+        assert_eq!(passthrough.fragment.as_deref(), Some("macos"));
+
+        let mut passthrough = Passthrough::default();
+        assert_eq!(passthrough.split_fragment("setup#"), "setup");
+        assert_eq!(passthrough.fragment, None);
+    }
+
+    #[test]
+    fn unparseable_target_is_left_alone() {
+        let link = resolved("not a url", Forwarding::Request);
+        let passthrough = passthrough("query=x&from=now-1h", "x");
+        assert_eq!(forward(&link, &passthrough), "not a url");
+    }
+}
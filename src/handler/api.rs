@@ -4,19 +4,39 @@ use axum::{
     Json, Router,
     extract::{Path, Query, State},
     http::StatusCode,
-    routing::get,
+    routing::{delete, get, post, put},
 };
 use serde_json::{Value, json};
 use sqlx::Error;
 
 use crate::{
     AppState,
+    identity::{Forbidden, Identity},
     model::Paging,
     schema::{
-        CreateLink, DeleteLink, FindLink, GetLink, PagingOptions, QueryLinks, SearchOptions,
-        UpdateLink,
+        AddCoOwner, AddSchedule, AddVariant, AnnotateItem, ClusterTopics, CoOwner, CollectLink,
+        CreateLink, CreateSynonym, CreateTag, DeleteLink, DeleteSynonym, DeleteTag, DeprecateLink,
+        FindLink, FindLinksByTarget, GetLink, GetRevision, GetSynonym, GetTag, GetTopic,
+        ListRevisions, OrderItems, PagingOptions, QueryLinks, QueryTopicLinks, RemoveCoOwner,
+        RemoveCollectionItem, RemoveSchedule, RemoveVariant, RenameLink, ReorderCollectionItems,
+        ScheduleTarget, SearchOptions, SetTags, SetVisibility, SortOptions, SplitTarget, TagLink,
+        TransferLink, UntagLink, UntrashLink, UpdateCollectionItem, UpdateLink, UpdateSynonym,
+        UpdateTag,
     },
-    service::{create_link, delete_link, edit_link, find_link, get_link, query_links},
+    service::{
+        add_co_owner, add_collection_item, add_schedule, add_variant, cluster_topics, create_link,
+        create_synonym, create_tag, delete_link, delete_synonym, delete_tag, deprecate_link,
+        edit_link, edit_synonym, edit_tag, find_link, find_links_by_target, get_link, get_synonym,
+        get_tag, get_topic, list_dependent_aliases, list_namespaces, list_personal_links,
+        list_revisions, list_synonyms, list_tags, list_topics, list_trash, query_links,
+        query_topic_links, remove_co_owner, remove_collection_item, remove_schedule,
+        remove_variant, rename_link, reorder_collection_items, restore_link, restore_revision,
+        set_link_tags, set_visibility, tag_link, transfer_link, undeprecate_link, untag_link,
+        update_collection_item,
+    },
+    source::SourceError,
+    tag::TagError,
+    target::TargetError,
 };
 
 fn db_err(err: Error) -> (StatusCode, Json<Value>) {
@@ -33,6 +53,34 @@ fn db_err(err: Error) -> (StatusCode, Json<Value>) {
             StatusCode::NOT_FOUND,
             Json(json!({"message": format!("Not found: {}", err)})),
         ),
+        Error::Encode(err) if err.is::<Forbidden>() => (
+            StatusCode::FORBIDDEN,
+            Json(json!({"message": format!("Forbidden: {}", err)})),
+        ),
+        Error::Encode(err) if err.is::<TargetError>() => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({"message": format!("Invalid target: {}", err)})),
+        ),
+        Error::Encode(err) if err.is::<SourceError>() => {
+            let violation = err.downcast_ref::<SourceError>();
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({
+                    "message": format!("Invalid source: {}", err),
+                    "error": violation,
+                })),
+            )
+        }
+        Error::Encode(err) if err.is::<TagError>() => {
+            let violation = err.downcast_ref::<TagError>();
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({
+                    "message": format!("Invalid tag: {}", err),
+                    "error": violation,
+                })),
+            )
+        }
         Error::Encode(err)
         | Error::Decode(err)
         | Error::ColumnDecode {
@@ -47,7 +95,7 @@ fn db_err(err: Error) -> (StatusCode, Json<Value>) {
             Json(json!({"message": format!("Failed to read/write IO: {}", err)})),
         ),
         Error::Database(err) => {
-            if err.message().contains("Duplicate entry") {
+            if err.is_unique_violation() {
                 let error_response = json!({
                     "message": format!("Already exists: {}", err),
                 });
@@ -79,15 +127,20 @@ async fn health_check_handler() -> Json<Value> {
 
 async fn query_links_handler(
     State(app_state): State<Arc<AppState>>,
+    identity: Identity,
     Query(paging): Query<PagingOptions>,
     Query(search): Query<SearchOptions>,
+    Query(sort): Query<SortOptions>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     let query = QueryLinks {
         paging,
         search: search.clone(),
+        sort,
+        identity,
+        ..Default::default()
     };
     let (links, last) = query_links(&app_state, &query).await.map_err(db_err)?;
-    let paging = Paging::new(&paging, &search, last, "/api/links", "");
+    let paging = Paging::new(&paging, &search, last, "/api/links", "").sorted(&sort);
 
     let json_response = json!({
         "paging": paging,
@@ -99,9 +152,12 @@ async fn query_links_handler(
 
 async fn create_link_handler(
     State(app_state): State<Arc<AppState>>,
+    identity: Identity,
     Json(body): Json<CreateLink>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let link = create_link(&app_state, &body).await.map_err(db_err)?;
+    let link = create_link(&app_state, &identity, &body)
+        .await
+        .map_err(db_err)?;
 
     let link_response = json!({
         "link": link,
@@ -112,9 +168,10 @@ async fn create_link_handler(
 
 async fn get_link_handler(
     State(app_state): State<Arc<AppState>>,
+    identity: Identity,
     Path(id): Path<i64>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let link = get_link(&app_state, &GetLink { id })
+    let link = get_link(&app_state, &GetLink { id, identity })
         .await
         .map_err(db_err)?;
 
@@ -127,13 +184,16 @@ async fn get_link_handler(
 
 async fn find_link_handler(
     State(app_state): State<Arc<AppState>>,
+    identity: Identity,
     Query(search): Query<SearchOptions>,
     Query(paging): Query<PagingOptions>,
+    Query(sort): Query<SortOptions>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     let link = find_link(
         &app_state,
         &FindLink {
             source: search.query.clone(),
+            identity: identity.clone(),
         },
     )
     .await
@@ -145,18 +205,34 @@ async fn find_link_handler(
 
         Ok(Json(link_response))
     } else {
-        query_links_handler(State(app_state), Query(paging), Query(search)).await
+        query_links_handler(
+            State(app_state),
+            identity,
+            Query(paging),
+            Query(search),
+            Query(sort),
+        )
+        .await
     }
 }
 
 async fn edit_link_handler(
     State(app_state): State<Arc<AppState>>,
+    identity: Identity,
     Path(id): Path<i64>,
     Json(body): Json<UpdateLink>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
-    let updated_link = edit_link(&app_state, &GetLink { id }, &body)
-        .await
-        .map_err(db_err)?;
+    let updated_link = edit_link(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
 
     let link_response = json!({
         "link": updated_link,
@@ -167,25 +243,820 @@ async fn edit_link_handler(
 
 async fn delete_link_handler(
     State(app_state): State<Arc<AppState>>,
+    identity: Identity,
     Path(id): Path<i64>,
 ) -> Result<StatusCode, (StatusCode, Json<Value>)> {
-    delete_link(&app_state, &DeleteLink { id })
+    delete_link(&app_state, &identity, &DeleteLink { id })
         .await
         .map_err(db_err)?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
-pub fn router(app_state: Arc<AppState>) -> Router {
-    Router::new()
-        .route("/healthcheck", get(health_check_handler))
-        .route("/links", get(query_links_handler).post(create_link_handler))
-        .route(
-            "/link/{id}",
-            get(get_link_handler)
-                .put(edit_link_handler)
-                .delete(delete_link_handler),
-        )
-        .route("/search/{alias}", get(find_link_handler))
+async fn restore_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let link = restore_link(
+        &app_state,
+        &identity,
+        &UntrashLink {
+            id,
+            identity: identity.clone(),
+        },
+    )
+    .await
+    .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn list_trash_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let links = list_trash(&app_state, &identity).await.map_err(db_err)?;
+
+    let json_response = json!({
+        "links": links,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn list_namespaces_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let namespaces = list_namespaces(&app_state).await.map_err(db_err)?;
+
+    let json_response = json!({
+        "namespaces": namespaces,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn list_topics_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let topics = list_topics(&app_state).await.map_err(db_err)?;
+
+    let json_response = json!({
+        "topics": topics,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn cluster_topics_handler(
+    State(app_state): State<Arc<AppState>>,
+    Query(cluster): Query<ClusterTopics>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let topics = cluster_topics(&app_state, &cluster).await.map_err(db_err)?;
+
+    let json_response = json!({
+        "topics": topics,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn get_topic_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(paging): Query<PagingOptions>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let topic = get_topic(&app_state, &GetTopic { id })
+        .await
+        .map_err(db_err)?;
+    let (links, last) = query_topic_links(&app_state, &QueryTopicLinks { id, paging })
+        .await
+        .map_err(db_err)?;
+    let paging = Paging::new(
+        &paging,
+        &SearchOptions::default(),
+        last,
+        &format!("/api/topic/{id}"),
+        "",
+    );
+
+    let json_response = json!({
+        "topic": topic,
+        "paging": paging,
+        "links": links,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn list_synonyms_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let synonyms = list_synonyms(&app_state).await.map_err(db_err)?;
+
+    let json_response = json!({
+        "synonyms": synonyms,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn create_synonym_handler(
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateSynonym>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let synonym = create_synonym(&app_state, &body).await.map_err(db_err)?;
+
+    let synonym_response = json!({
+        "synonym": synonym,
+    });
+
+    Ok(Json(synonym_response))
+}
+
+async fn get_synonym_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let synonym = get_synonym(&app_state, &GetSynonym { id })
+        .await
+        .map_err(db_err)?;
+
+    let synonym_response = json!({
+        "synonym": synonym,
+    });
+
+    Ok(Json(synonym_response))
+}
+
+async fn edit_synonym_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(body): Json<UpdateSynonym>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let synonym = edit_synonym(&app_state, &GetSynonym { id }, &body)
+        .await
+        .map_err(db_err)?;
+
+    let synonym_response = json!({
+        "synonym": synonym,
+    });
+
+    Ok(Json(synonym_response))
+}
+
+async fn delete_synonym_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    delete_synonym(&app_state, &DeleteSynonym { id })
+        .await
+        .map_err(db_err)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn transfer_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<TransferLink>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let link = transfer_link(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn list_revisions_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let revisions = list_revisions(
+        &app_state,
+        &ListRevisions {
+            link_id: id,
+            identity,
+        },
+    )
+    .await
+    .map_err(db_err)?;
+
+    let json_response = json!({
+        "revisions": revisions
+            .iter()
+            .map(|revision| json!({
+                "revision": revision,
+                "diffs": revision.diffs(),
+            }))
+            .collect::<Vec<_>>(),
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn restore_revision_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let link = restore_revision(
+        &app_state,
+        &identity,
+        &GetRevision {
+            id,
+            identity: identity.clone(),
+        },
+    )
+    .await
+    .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn list_personal_links_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let links = list_personal_links(&app_state, &identity)
+        .await
+        .map_err(db_err)?;
+
+    let json_response = json!({
+        "links": links,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn find_links_by_target_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Query(find): Query<FindLinksByTarget>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let find = FindLinksByTarget { identity, ..find };
+    let links = find_links_by_target(&app_state, &find)
+        .await
+        .map_err(db_err)?;
+
+    let json_response = json!({
+        "links": links,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn rename_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<RenameLink>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let (link, aliases) = rename_link(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+        "aliases": aliases,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn list_dependent_aliases_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let links = list_dependent_aliases(&app_state, &GetLink { id, identity })
+        .await
+        .map_err(db_err)?;
+
+    let json_response = json!({
+        "links": links,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn deprecate_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<DeprecateLink>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let link = deprecate_link(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn set_visibility_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<SetVisibility>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let link = set_visibility(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn undeprecate_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let link = undeprecate_link(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+    )
+    .await
+    .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn schedule_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<ScheduleTarget>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let add = AddSchedule {
+        link_id: id,
+        target: body.target,
+        active_from: body.active_from,
+    };
+    let link = add_schedule(&app_state, &identity, &add)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn unschedule_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((link_id, id)): Path<(i64, i64)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let remove = RemoveSchedule { link_id, id };
+    let link = remove_schedule(&app_state, &identity, &remove)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn split_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<SplitTarget>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let add = AddVariant {
+        link_id: id,
+        target: body.target,
+        weight: body.weight,
+    };
+    let link = add_variant(&app_state, &identity, &add)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn unsplit_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((link_id, id)): Path<(i64, i64)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let remove = RemoveVariant { link_id, id };
+    let link = remove_variant(&app_state, &identity, &remove)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn list_collection_items_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let link = get_link(&app_state, &GetLink { id, identity })
+        .await
+        .map_err(db_err)?;
+
+    let items_response = json!({
+        "items": link.items,
+    });
+
+    Ok(Json(items_response))
+}
+
+async fn collect_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<CollectLink>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let link = add_collection_item(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn annotate_item_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((link_id, id)): Path<(i64, i64)>,
+    Json(body): Json<AnnotateItem>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let update = UpdateCollectionItem {
+        collection_id: link_id,
+        id,
+        note: body.note,
+    };
+    let link = update_collection_item(&app_state, &identity, &update)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn uncollect_item_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((link_id, id)): Path<(i64, i64)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let remove = RemoveCollectionItem {
+        collection_id: link_id,
+        id,
+    };
+    let link = remove_collection_item(&app_state, &identity, &remove)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn order_items_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<OrderItems>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let reorder = ReorderCollectionItems {
+        collection_id: id,
+        item_ids: body.item_ids,
+    };
+    let link = reorder_collection_items(&app_state, &identity, &reorder)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn add_co_owner_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<CoOwner>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let add = AddCoOwner {
+        link_id: id,
+        owner: body.owner,
+    };
+    let link = add_co_owner(&app_state, &identity, &add)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn remove_co_owner_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((id, owner)): Path<(i64, String)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let remove = RemoveCoOwner { link_id: id, owner };
+    let link = remove_co_owner(&app_state, &identity, &remove)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn list_tags_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let tags = list_tags(&app_state).await.map_err(db_err)?;
+
+    let json_response = json!({
+        "tags": tags,
+    });
+
+    Ok(Json(json_response))
+}
+
+async fn create_tag_handler(
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateTag>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let tag = create_tag(&app_state, &body).await.map_err(db_err)?;
+
+    let tag_response = json!({
+        "tag": tag,
+    });
+
+    Ok(Json(tag_response))
+}
+
+async fn get_tag_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let tag = get_tag(&app_state, &GetTag { id }).await.map_err(db_err)?;
+
+    let tag_response = json!({
+        "tag": tag,
+    });
+
+    Ok(Json(tag_response))
+}
+
+async fn edit_tag_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(body): Json<UpdateTag>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let tag = edit_tag(&app_state, &GetTag { id }, &body)
+        .await
+        .map_err(db_err)?;
+
+    let tag_response = json!({
+        "tag": tag,
+    });
+
+    Ok(Json(tag_response))
+}
+
+async fn delete_tag_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    delete_tag(&app_state, &DeleteTag { id })
+        .await
+        .map_err(db_err)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn tag_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<CreateTag>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let tag = TagLink {
+        link_id: id,
+        name: body.name,
+    };
+    let link = tag_link(&app_state, &identity, &tag)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn set_link_tags_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Json(body): Json<SetTags>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let link = set_link_tags(&app_state, &GetLink { id, identity }, &body)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+async fn untag_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((id, name)): Path<(i64, String)>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let untag = UntagLink { link_id: id, name };
+    let link = untag_link(&app_state, &identity, &untag)
+        .await
+        .map_err(db_err)?;
+
+    let link_response = json!({
+        "link": link,
+    });
+
+    Ok(Json(link_response))
+}
+
+pub fn router(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/healthcheck", get(health_check_handler))
+        .route("/links", get(query_links_handler).post(create_link_handler))
+        .route("/links/by-target", get(find_links_by_target_handler))
+        .route("/links/mine", get(list_personal_links_handler))
+        .route(
+            "/link/{id}",
+            get(get_link_handler)
+                .put(edit_link_handler)
+                .delete(delete_link_handler),
+        )
+        .route(
+            "/link/{id}/tags",
+            post(tag_link_handler).put(set_link_tags_handler),
+        )
+        .route("/link/{id}/tag/{name}", delete(untag_link_handler))
+        .route("/link/{id}/owner", put(transfer_link_handler))
+        .route("/link/{id}/rename", post(rename_link_handler))
+        .route("/link/{id}/aliases", get(list_dependent_aliases_handler))
+        .route("/link/{id}/schedules", post(schedule_link_handler))
+        .route(
+            "/link/{id}/deprecation",
+            put(deprecate_link_handler).delete(undeprecate_link_handler),
+        )
+        .route("/link/{id}/visibility", put(set_visibility_handler))
+        .route(
+            "/link/{link_id}/schedule/{id}",
+            delete(unschedule_link_handler),
+        )
+        .route("/link/{id}/variants", post(split_link_handler))
+        .route("/link/{link_id}/variant/{id}", delete(unsplit_link_handler))
+        .route(
+            "/link/{id}/items",
+            get(list_collection_items_handler).post(collect_link_handler),
+        )
+        .route("/link/{id}/items/order", put(order_items_handler))
+        .route(
+            "/link/{link_id}/item/{id}",
+            put(annotate_item_handler).delete(uncollect_item_handler),
+        )
+        .route("/link/{id}/restore", post(restore_link_handler))
+        .route("/link/{id}/revisions", get(list_revisions_handler))
+        .route("/revision/{id}/restore", post(restore_revision_handler))
+        .route("/link/{id}/co-owners", post(add_co_owner_handler))
+        .route(
+            "/link/{id}/co-owner/{owner}",
+            delete(remove_co_owner_handler),
+        )
+        .route("/search/{alias}", get(find_link_handler))
+        .route("/trash", get(list_trash_handler))
+        .route("/namespaces", get(list_namespaces_handler))
+        .route(
+            "/topics",
+            get(list_topics_handler).post(cluster_topics_handler),
+        )
+        .route("/topic/{id}", get(get_topic_handler))
+        .route("/tags", get(list_tags_handler).post(create_tag_handler))
+        .route(
+            "/tag/{id}",
+            get(get_tag_handler)
+                .put(edit_tag_handler)
+                .delete(delete_tag_handler),
+        )
+        .route(
+            "/synonyms",
+            get(list_synonyms_handler).post(create_synonym_handler),
+        )
+        .route(
+            "/synonym/{id}",
+            get(get_synonym_handler)
+                .put(edit_synonym_handler)
+                .delete(delete_synonym_handler),
+        )
         .with_state(app_state)
 }
@@ -4,28 +4,121 @@ use askama::Template;
 use axum::{
     Form, Router,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse},
-    routing::get,
+    routing::{delete, get, post, put},
 };
 
 use crate::{
     AppState,
-    model::Paging,
+    filter::{Clause, Filter, Term},
+    identity::{Forbidden, Identity},
+    model::{Forwarding, Paging},
     schema::{
-        CreateLink, DeleteLink, FindLink, GetLink, PagingOptions, QueryLinks, SearchOptions,
-        UpdateLink, ViewOptions,
+        AddSchedule, AddVariant, AnnotateItem, CollectLink, CreateLink, CreateSynonym, CreateTag,
+        DeleteLink, DeleteSynonym, DeprecateLink, FindLink, FindLinksByTarget, GetLink,
+        GetRevision, GetSynonym, ListRevisions, OrderItems, PagingOptions, QueryLinks,
+        QueryTopicLinks, RemoveCollectionItem, RemoveSchedule, RemoveVariant, RenameLink,
+        ReorderCollectionItems, ScheduleTarget, SearchOptions, SetVisibility, SortOptions,
+        SplitTarget, TagLink, UntagLink, UntrashLink, UpdateCollectionItem, UpdateLink,
+        UpdateSynonym, ViewOptions,
+    },
+    service::{
+        add_collection_item, add_schedule, add_variant, create_link, create_synonym, delete_link,
+        delete_synonym, deprecate_link, edit_link, edit_synonym, find_link, find_links_by_target,
+        get_link, get_synonym, list_dependent_aliases, list_namespaces, list_personal_links,
+        list_revisions, list_synonyms, list_topics, list_trash, query_links, query_topic_links,
+        remove_collection_item, remove_schedule, remove_variant, rename_link,
+        reorder_collection_items, restore_link, restore_revision, set_visibility, tag_link,
+        undeprecate_link, untag_link, update_collection_item,
+    },
+    source::SourceError,
+    tag::{self, TagError},
+    target::TargetError,
+    template::{
+        AlertTemplate, ByTargetTemplate, CollectionEditTemplate, DeletedTemplate,
+        DeprecationEditTemplate, EditTemplate, ErrorTemplate, LinksTemplate, ListTemplate,
+        NamespacesPageTemplate, NamespacesTemplate, RevisionsTemplate, SchedulesEditTemplate,
+        SynonymEditTemplate, SynonymViewTemplate, SynonymsPageTemplate, SynonymsTemplate,
+        TagsEditTemplate, TopicsPageTemplate, TopicsTemplate, TrashPageTemplate, TrashTemplate,
+        VariantsEditTemplate, ViewTemplate, VisibilityEditTemplate,
     },
-    service::{create_link, delete_link, edit_link, find_link, get_link, query_links},
-    template::{EditTemplate, ErrorTemplate, LinksTemplate, ListTemplate, ViewTemplate},
 };
 
 fn db_err(err: sqlx::Error) -> (StatusCode, Html<String>) {
     eprintln!("{err}");
-    (
-        StatusCode::BAD_REQUEST,
-        Html(ErrorTemplate {}.render().unwrap_or("Oops!".to_string())),
-    )
+    match err {
+        sqlx::Error::Encode(err) if err.is::<Forbidden>() => {
+            let message = format!("Forbidden: {err}");
+            (
+                StatusCode::FORBIDDEN,
+                Html(
+                    AlertTemplate { message }
+                        .render()
+                        .unwrap_or(err.to_string()),
+                ),
+            )
+        }
+        sqlx::Error::Encode(err) if err.is::<TargetError>() => {
+            let message = format!("Invalid target: {err}");
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Html(
+                    AlertTemplate { message }
+                        .render()
+                        .unwrap_or(err.to_string()),
+                ),
+            )
+        }
+        sqlx::Error::Encode(err) if err.is::<SourceError>() => {
+            let message = format!("Invalid source: {err}");
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Html(
+                    AlertTemplate { message }
+                        .render()
+                        .unwrap_or(err.to_string()),
+                ),
+            )
+        }
+        sqlx::Error::Encode(err) if err.is::<TagError>() => {
+            let message = format!("Invalid tag: {err}");
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Html(
+                    AlertTemplate { message }
+                        .render()
+                        .unwrap_or(err.to_string()),
+                ),
+            )
+        }
+        sqlx::Error::RowNotFound => {
+            let message = "Not found: it doesn't exist, or isn't visible to you".to_string();
+            (
+                StatusCode::NOT_FOUND,
+                Html(
+                    AlertTemplate { message }
+                        .render()
+                        .unwrap_or(err.to_string()),
+                ),
+            )
+        }
+        sqlx::Error::Database(err) if err.is_unique_violation() => {
+            let message = "Already exists: that source is taken by another link".to_string();
+            (
+                StatusCode::CONFLICT,
+                Html(
+                    AlertTemplate { message }
+                        .render()
+                        .unwrap_or(err.to_string()),
+                ),
+            )
+        }
+        _ => (
+            StatusCode::BAD_REQUEST,
+            Html(ErrorTemplate {}.render().unwrap_or("Oops!".to_string())),
+        ),
+    }
 }
 
 fn tp_err(err: askama::Error) -> (StatusCode, Html<String>) {
@@ -39,51 +132,152 @@ fn tp_err(err: askama::Error) -> (StatusCode, Html<String>) {
 pub async fn index_handler(
     Query(paging): Query<PagingOptions>,
     Query(search): Query<SearchOptions>,
+    Query(sort): Query<SortOptions>,
 ) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
-    let template_response = LinksTemplate { paging, search }.render().map_err(tp_err)?;
+    let template_response = LinksTemplate {
+        paging,
+        search,
+        sort,
+    }
+    .render()
+    .map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+pub async fn topics_page_handler() -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let template_response = TopicsPageTemplate {
+        search: SearchOptions::default(),
+    }
+    .render()
+    .map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+pub async fn trash_page_handler() -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let template_response = TrashPageTemplate {
+        search: SearchOptions::default(),
+    }
+    .render()
+    .map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+pub async fn namespaces_page_handler() -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let template_response = NamespacesPageTemplate {
+        search: SearchOptions::default(),
+    }
+    .render()
+    .map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+/// The links in a namespace, as a search for `ns:<namespace>`
+pub async fn namespace_page_handler(
+    Path(namespace): Path<String>,
+    Query(paging): Query<PagingOptions>,
+    Query(sort): Query<SortOptions>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let search = SearchOptions {
+        query: format!("ns:{namespace}"),
+        ..Default::default()
+    };
+    let template_response = LinksTemplate {
+        paging,
+        search,
+        sort,
+    }
+    .render()
+    .map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+pub async fn synonyms_page_handler() -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let template_response = SynonymsPageTemplate {
+        search: SearchOptions::default(),
+    }
+    .render()
+    .map_err(tp_err)?;
 
     Ok(Html(template_response))
 }
 
 async fn query_links_handler(
     State(app_state): State<Arc<AppState>>,
+    identity: Identity,
     Query(paging): Query<PagingOptions>,
     Query(search): Query<SearchOptions>,
+    Query(sort): Query<SortOptions>,
 ) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let filter = Filter::parse(&search.query);
     let find = FindLink {
-        source: search.query.clone(),
+        source: filter.text.clone(),
+        identity: identity.clone(),
     };
     let link = find_link(&app_state, &find).await.map_err(db_err)?;
     let new = match link {
-        None if !find.source.is_empty() => Some(CreateLink {
+        None if !find.source.is_empty() && filter.clauses.is_empty() => Some(CreateLink {
             source: find.source,
             is_alias: false,
             target: "".to_string(),
+            description: "".to_string(),
+            valid_from: None,
+            valid_until: None,
+            personal: false,
+            is_collection: false,
+            redirect_status: None,
+            forwarding: Forwarding::Off,
         }),
         _ => None,
     };
 
+    let mine = if search.query.is_empty() && paging.page == 1 {
+        list_personal_links(&app_state, &identity)
+            .await
+            .map_err(db_err)?
+    } else {
+        vec![]
+    };
+
     let query = QueryLinks {
         paging,
         search: search.clone(),
+        sort,
+        identity,
+        ..Default::default()
     };
-    let (links, last) = query_links(&app_state, &query).await.map_err(db_err)?;
+    let (mut links, last) = query_links(&app_state, &query).await.map_err(db_err)?;
+    if !mine.is_empty() {
+        links.retain(|link| link.personal_to.is_none());
+    }
 
-    let paging = Paging::new(&paging, &search, last, "/go/links", "#links");
+    let paging = Paging::new(&paging, &search, last, "/go/links", "#links").sorted(&sort);
     let hx_push_url = paging.full_query();
 
-    let template_response = ListTemplate { new, links, paging }
-        .render()
-        .map_err(tp_err)?;
+    let template_response = ListTemplate {
+        new,
+        mine,
+        links,
+        paging,
+    }
+    .render()
+    .map_err(tp_err)?;
 
     Ok(([("HX-Push-Url", hx_push_url)], Html(template_response)))
 }
 
 async fn create_link_handler(
     State(app_state): State<Arc<AppState>>,
+    identity: Identity,
     Form(body): Form<CreateLink>,
 ) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
-    let link = create_link(&app_state, &body).await.map_err(db_err)?;
+    let link = create_link(&app_state, &identity, &body)
+        .await
+        .map_err(db_err)?;
 
     let template_response = ViewTemplate { link }.render().map_err(tp_err)?;
 
@@ -92,56 +286,661 @@ async fn create_link_handler(
 
 async fn edit_link_handler(
     State(app_state): State<Arc<AppState>>,
+    identity: Identity,
     Path(id): Path<i64>,
     Form(body): Form<UpdateLink>,
 ) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
-    let link = edit_link(&app_state, &GetLink { id }, &body)
+    let link = edit_link(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = ViewTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn list_revisions_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let revisions = list_revisions(
+        &app_state,
+        &ListRevisions {
+            link_id: id,
+            identity,
+        },
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = RevisionsTemplate {
+        link_id: id,
+        revisions,
+    }
+    .render()
+    .map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn restore_revision_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let link = restore_revision(
+        &app_state,
+        &identity,
+        &GetRevision {
+            id,
+            identity: identity.clone(),
+        },
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = ViewTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn get_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    view: Query<ViewOptions>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let link = get_link(
+        &app_state,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = if view.editable {
+        let aliases = list_dependent_aliases(
+            &app_state,
+            &GetLink {
+                id,
+                identity: identity.clone(),
+            },
+        )
         .await
         .map_err(db_err)?;
+        EditTemplate { link, aliases }.render()
+    } else {
+        ViewTemplate { link }.render()
+    }
+    .map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn delete_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let link = delete_link(&app_state, &identity, &DeleteLink { id })
+        .await
+        .map_err(db_err)?;
+
+    let template_response = DeletedTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn restore_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let link = restore_link(
+        &app_state,
+        &identity,
+        &UntrashLink {
+            id,
+            identity: identity.clone(),
+        },
+    )
+    .await
+    .map_err(db_err)?;
 
     let template_response = ViewTemplate { link }.render().map_err(tp_err)?;
 
     Ok(Html(template_response))
 }
 
-async fn get_link_handler(
+async fn list_trash_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let links = list_trash(&app_state, &identity).await.map_err(db_err)?;
+
+    let template_response = TrashTemplate { links }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn list_namespaces_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let namespaces = list_namespaces(&app_state).await.map_err(db_err)?;
+
+    let template_response = NamespacesTemplate { namespaces }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn list_topics_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let topics = list_topics(&app_state).await.map_err(db_err)?;
+
+    let template_response = TopicsTemplate { topics }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn query_topic_links_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(paging): Query<PagingOptions>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let query = QueryTopicLinks { id, paging };
+    let (links, last) = query_topic_links(&app_state, &query)
+        .await
+        .map_err(db_err)?;
+
+    let paging = Paging::new(
+        &paging,
+        &SearchOptions::default(),
+        last,
+        &format!("/go/topic/{id}"),
+        "#links",
+    );
+
+    let template_response = ListTemplate {
+        new: None,
+        mine: vec![],
+        links,
+        paging,
+    }
+    .render()
+    .map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn list_synonyms_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let synonyms = list_synonyms(&app_state).await.map_err(db_err)?;
+
+    let template_response = SynonymsTemplate { synonyms }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn create_synonym_handler(
+    State(app_state): State<Arc<AppState>>,
+    Form(body): Form<CreateSynonym>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let synonym = create_synonym(&app_state, &body).await.map_err(db_err)?;
+
+    let template_response = SynonymViewTemplate { synonym }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn get_synonym_handler(
     State(app_state): State<Arc<AppState>>,
     Path(id): Path<i64>,
     view: Query<ViewOptions>,
 ) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
-    let link = get_link(&app_state, &GetLink { id })
+    let synonym = get_synonym(&app_state, &GetSynonym { id })
         .await
         .map_err(db_err)?;
 
     let template_response = if view.editable {
-        EditTemplate { link }.render()
+        SynonymEditTemplate { synonym }.render()
     } else {
-        ViewTemplate { link }.render()
+        SynonymViewTemplate { synonym }.render()
     }
     .map_err(tp_err)?;
 
     Ok(Html(template_response))
 }
 
-async fn delete_link_handler(
+async fn edit_synonym_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Form(body): Form<UpdateSynonym>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let synonym = edit_synonym(&app_state, &GetSynonym { id }, &body)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = SynonymViewTemplate { synonym }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn delete_synonym_handler(
     State(app_state): State<Arc<AppState>>,
     Path(id): Path<i64>,
 ) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
-    delete_link(&app_state, &DeleteLink { id })
+    delete_synonym(&app_state, &DeleteSynonym { id })
         .await
         .map_err(db_err)?;
 
     Ok(Html(()))
 }
 
+async fn query_tag_links_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(tag): Path<String>,
+    Query(paging): Query<PagingOptions>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let tag = tag.trim().to_lowercase();
+    tag::check(&tag).map_err(|err| db_err(sqlx::Error::Encode(Box::new(err))))?;
+
+    if !headers.contains_key("HX-Request") {
+        let template_response = LinksTemplate {
+            paging,
+            search: SearchOptions {
+                query: format!("tag:{tag}"),
+                ..Default::default()
+            },
+            sort: SortOptions::default(),
+        }
+        .render()
+        .map_err(tp_err)?;
+
+        return Ok(Html(template_response));
+    }
+
+    let search = SearchOptions::default();
+    let query = QueryLinks {
+        paging,
+        search: search.clone(),
+        filter: Filter {
+            clauses: vec![Clause {
+                negated: false,
+                term: Term::Tagged(tag.clone()),
+            }],
+            ..Default::default()
+        },
+        identity,
+        ..Default::default()
+    };
+    let (links, last) = query_links(&app_state, &query).await.map_err(db_err)?;
+
+    let paging = Paging::new(&paging, &search, last, &format!("/go/tags/{tag}"), "#links");
+
+    let template_response = ListTemplate {
+        new: None,
+        mine: vec![],
+        links,
+        paging,
+    }
+    .render()
+    .map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn tag_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Form(body): Form<CreateTag>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let tag = TagLink {
+        link_id: id,
+        name: body.name,
+    };
+    let link = tag_link(&app_state, &identity, &tag)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = TagsEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn schedule_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Form(body): Form<ScheduleTarget>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let add = AddSchedule {
+        link_id: id,
+        target: body.target,
+        active_from: body.active_from,
+    };
+    let link = add_schedule(&app_state, &identity, &add)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = SchedulesEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn unschedule_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((link_id, id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let remove = RemoveSchedule { link_id, id };
+    let link = remove_schedule(&app_state, &identity, &remove)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = SchedulesEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn split_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Form(body): Form<SplitTarget>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let add = AddVariant {
+        link_id: id,
+        target: body.target,
+        weight: body.weight,
+    };
+    let link = add_variant(&app_state, &identity, &add)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = VariantsEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn unsplit_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((link_id, id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let remove = RemoveVariant { link_id, id };
+    let link = remove_variant(&app_state, &identity, &remove)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = VariantsEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn collect_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Form(body): Form<CollectLink>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let link = add_collection_item(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = CollectionEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn annotate_item_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((link_id, id)): Path<(i64, i64)>,
+    Form(body): Form<AnnotateItem>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let update = UpdateCollectionItem {
+        collection_id: link_id,
+        id,
+        note: body.note,
+    };
+    let link = update_collection_item(&app_state, &identity, &update)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = CollectionEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn uncollect_item_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((link_id, id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let remove = RemoveCollectionItem {
+        collection_id: link_id,
+        id,
+    };
+    let link = remove_collection_item(&app_state, &identity, &remove)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = CollectionEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn order_items_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Form(body): Form<OrderItems>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let reorder = ReorderCollectionItems {
+        collection_id: id,
+        item_ids: body.item_ids,
+    };
+    let link = reorder_collection_items(&app_state, &identity, &reorder)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = CollectionEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn find_links_by_target_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Query(find): Query<FindLinksByTarget>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let find = FindLinksByTarget { identity, ..find };
+    let links = find_links_by_target(&app_state, &find)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = ByTargetTemplate { links }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn rename_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Form(body): Form<RenameLink>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let (link, _) = rename_link(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = ViewTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn deprecate_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Form(body): Form<DeprecateLink>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let link = deprecate_link(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = DeprecationEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn set_visibility_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+    Form(body): Form<SetVisibility>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let link = set_visibility(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+        &body,
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = VisibilityEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn undeprecate_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let link = undeprecate_link(
+        &app_state,
+        &identity,
+        &GetLink {
+            id,
+            identity: identity.clone(),
+        },
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = DeprecationEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
+async fn untag_link_handler(
+    State(app_state): State<Arc<AppState>>,
+    identity: Identity,
+    Path((id, name)): Path<(i64, String)>,
+) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
+    let untag = UntagLink { link_id: id, name };
+    let link = untag_link(&app_state, &identity, &untag)
+        .await
+        .map_err(db_err)?;
+
+    let template_response = TagsEditTemplate { link }.render().map_err(tp_err)?;
+
+    Ok(Html(template_response))
+}
+
 pub fn router(app_state: Arc<AppState>) -> Router {
     Router::new()
         .route("/links", get(query_links_handler).post(create_link_handler))
+        .route("/links/by-target", get(find_links_by_target_handler))
         .route(
             "/link/{id}",
             get(get_link_handler)
                 .delete(delete_link_handler)
                 .put(edit_link_handler),
         )
+        .route("/link/{id}/tags", post(tag_link_handler))
+        .route("/link/{id}/tag/{name}", delete(untag_link_handler))
+        .route("/link/{id}/rename", post(rename_link_handler))
+        .route("/link/{id}/schedules", post(schedule_link_handler))
+        .route(
+            "/link/{id}/deprecation",
+            put(deprecate_link_handler).delete(undeprecate_link_handler),
+        )
+        .route("/link/{id}/visibility", put(set_visibility_handler))
+        .route(
+            "/link/{link_id}/schedule/{id}",
+            delete(unschedule_link_handler),
+        )
+        .route("/link/{id}/variants", post(split_link_handler))
+        .route("/link/{link_id}/variant/{id}", delete(unsplit_link_handler))
+        .route("/link/{id}/items", post(collect_link_handler))
+        .route("/link/{id}/items/order", put(order_items_handler))
+        .route(
+            "/link/{link_id}/item/{id}",
+            put(annotate_item_handler).delete(uncollect_item_handler),
+        )
+        .route("/link/{id}/restore", post(restore_link_handler))
+        .route("/link/{id}/revisions", get(list_revisions_handler))
+        .route("/revision/{id}/restore", post(restore_revision_handler))
+        .route("/trash", get(list_trash_handler))
+        .route("/namespaces", get(list_namespaces_handler))
+        .route("/tags/{tag}", get(query_tag_links_handler))
+        .route("/topics", get(list_topics_handler))
+        .route("/topic/{id}", get(query_topic_links_handler))
+        .route(
+            "/synonyms",
+            get(list_synonyms_handler).post(create_synonym_handler),
+        )
+        .route(
+            "/synonym/{id}",
+            get(get_synonym_handler)
+                .delete(delete_synonym_handler)
+                .put(edit_synonym_handler),
+        )
         .with_state(app_state)
 }
@@ -3,13 +3,40 @@ use std::sync::Arc;
 use askama::Template;
 use axum::{
     Router,
-    extract::{Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Redirect},
+    extract::{Query, RawQuery, State},
+    http::{
+        HeaderValue, StatusCode,
+        header::{LOCATION, SET_COOKIE},
+    },
+    response::{Html, IntoResponse, Redirect, Response},
     routing::get,
 };
 
-use crate::{AppState, schema::SearchOptions, service::resolve_alias, template::ErrorTemplate};
+use crate::{
+    AppState,
+    forward::{Passthrough, route},
+    identity::{self, Identity, VISITOR_COOKIE},
+    model::{Link, now},
+    schema::{CreateClick, GetStats, PreviewOptions, SearchOptions},
+    service::{get_stats, record_click, resolve_alias, resolve_alias_chain},
+    template::{
+        CollectionTemplate, DeprecatedTemplate, ErrorTemplate, ExpiredTemplate, PreviewTemplate,
+    },
+};
+
+/// Seconds the deprecation interstitial waits before forwarding
+const DEPRECATION_COUNTDOWN: u64 = 5;
+
+/// Seconds the visitor cookie, and so a visitor's variants, last
+const VISITOR_MAX_AGE: u64 = 60 * 60 * 24 * 365;
+
+fn tp_err(err: askama::Error) -> (StatusCode, Html<String>) {
+    eprintln!("{err}");
+    (
+        StatusCode::BAD_REQUEST,
+        Html(ErrorTemplate {}.render().unwrap_or("Oops!".to_string())),
+    )
+}
 
 fn db_err(err: sqlx::Error) -> (StatusCode, Html<String>) {
     eprintln!("{err}");
@@ -19,19 +46,166 @@ fn db_err(err: sqlx::Error) -> (StatusCode, Html<String>) {
     )
 }
 
+fn visitor_cookie(visitor: &str) -> Option<HeaderValue> {
+    let cookie = format!(
+        "{VISITOR_COOKIE}={visitor}; Path=/; Max-Age={VISITOR_MAX_AGE}; HttpOnly; SameSite=Lax"
+    );
+    HeaderValue::from_str(&cookie).ok()
+}
+
+/// Redirect with a link's own status
+fn redirect(status: u16, target: &str) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::SEE_OTHER);
+    (status, [(LOCATION, target)]).into_response()
+}
+
+fn not_found(search: &SearchOptions) -> Response {
+    let path = format!("/?query={}&method={}", search.query, search.method);
+    Redirect::to(&path).into_response()
+}
+
+/// Show what a link leads to, without following it or counting a click
+async fn preview_link(
+    app_state: Arc<AppState>,
+    identity: &Identity,
+    search: SearchOptions,
+) -> Result<Response, (StatusCode, Html<String>)> {
+    let mut chain = resolve_alias_chain(app_state.clone(), identity, search.query.clone())
+        .await
+        .map_err(db_err)?;
+    let Some(link) = chain.pop() else {
+        return Ok(not_found(&search));
+    };
+    let stats = get_stats(
+        &app_state,
+        &GetStats {
+            link_id: link.id,
+            identity: identity.clone(),
+        },
+    )
+    .await
+    .map_err(db_err)?;
+
+    let template_response = PreviewTemplate {
+        link,
+        chain,
+        stats,
+        search,
+    }
+    .render()
+    .map_err(tp_err)?;
+    Ok(Html(template_response).into_response())
+}
+
 async fn find_link_handler(
     State(app_state): State<Arc<AppState>>,
-    Query(search): Query<SearchOptions>,
-) -> Result<impl IntoResponse, (StatusCode, Html<String>)> {
-    let resolved = resolve_alias(app_state, search.query.clone())
+    identity: Identity,
+    Query(mut search): Query<SearchOptions>,
+    Query(mut preview): Query<PreviewOptions>,
+    RawQuery(raw_query): RawQuery,
+) -> Result<Response, (StatusCode, Html<String>)> {
+    let mut passthrough = Passthrough::from_query(raw_query.as_deref().unwrap_or_default());
+    search.query = passthrough.split_fragment(&search.query).to_string();
+
+    // A trailing `+`, as in `go/wiki+`, asks for a preview too
+    if let Some(query) = search.query.trim_end().strip_suffix('+') {
+        search.query = query.trim_end().to_string();
+        preview.preview = true;
+    }
+    if preview.preview {
+        return preview_link(app_state, &identity, search).await;
+    }
+
+    let resolved = resolve_alias(app_state.clone(), &identity, search.query.clone())
         .await
         .map_err(db_err)?;
 
-    if let Some(link) = resolved {
-        Ok(Redirect::to(&link.target))
-    } else {
-        let path = format!("/?query={}&method={}", search.query, search.method);
-        Ok(Redirect::to(&path))
+    match resolved {
+        Some(link) if !link.is_active() => {
+            let status = if link.is_expired() {
+                StatusCode::GONE
+            } else {
+                StatusCode::NOT_FOUND
+            };
+            let template_response = ExpiredTemplate { link, search }.render().map_err(tp_err)?;
+            Ok((status, Html(template_response)).into_response())
+        }
+        Some(link) if link.is_collection => {
+            record_click(
+                &app_state,
+                click(&app_state, &identity, &link, &search, None),
+            );
+            let template_response = CollectionTemplate { link, search }
+                .render()
+                .map_err(tp_err)?;
+            Ok(Html(template_response).into_response())
+        }
+        Some(link)
+            if !link.is_alias && app_state.target_policy.normalize(&link.target).is_err() =>
+        {
+            eprintln!("🚫 Refusing to redirect to '{}'", link.target);
+            let template_response = ErrorTemplate {}.render().map_err(tp_err)?;
+            Ok((StatusCode::BAD_REQUEST, Html(template_response)).into_response())
+        }
+        Some(mut link) => {
+            // Anonymous visitors get a cookie to stay on their variant
+            let new_visitor = (!link.variants.is_empty() && identity.sticky_key().is_none())
+                .then(identity::new_visitor);
+            let sticky_key = identity
+                .sticky_key()
+                .or(new_visitor.as_deref())
+                .unwrap_or_default();
+            let variant = route(&mut link, sticky_key, &passthrough);
+
+            record_click(
+                &app_state,
+                click(
+                    &app_state,
+                    &identity,
+                    &link,
+                    &search,
+                    variant.map(|variant| variant.id),
+                ),
+            );
+            let mut response = if link.is_deprecated() {
+                let template_response = DeprecatedTemplate {
+                    link,
+                    search,
+                    countdown: DEPRECATION_COUNTDOWN,
+                }
+                .render()
+                .map_err(tp_err)?;
+                Html(template_response).into_response()
+            } else {
+                redirect(link.redirect_status, &link.target)
+            };
+            if let Some(cookie) = new_visitor.as_deref().and_then(visitor_cookie) {
+                response.headers_mut().append(SET_COOKIE, cookie);
+            }
+            Ok(response)
+        }
+        None => Ok(not_found(&search)),
+    }
+}
+
+/// A click on a resolved link, with the aliases it was reached through
+fn click(
+    app_state: &AppState,
+    identity: &Identity,
+    link: &Link,
+    search: &SearchOptions,
+    variant_id: Option<i64>,
+) -> CreateClick {
+    CreateClick {
+        link_id: link.id,
+        query: search.query.clone(),
+        variant_id,
+        alias_chain: link.via.iter().chain([&link.source]).cloned().collect(),
+        user_hash: app_state
+            .click_user_salt
+            .as_deref()
+            .and_then(|salt| identity.user_hash(salt)),
+        clicked_at: now(),
     }
 }
 
@@ -0,0 +1,126 @@
+use std::{
+    collections::hash_map::RandomState,
+    convert::Infallible,
+    fmt::Display,
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::COOKIE, request::Parts},
+};
+
+use openssl::sha::sha256;
+
+use crate::AppState;
+
+/// Cookie that keeps anonymous visitors on the same variant of a link
+pub const VISITOR_COOKIE: &str = "go_visitor";
+
+/// A fresh, practically unique, visitor id for the visitor cookie
+pub fn new_visitor() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    hasher.write_u128(nanos);
+    format!("{:016x}", hasher.finish())
+}
+
+/// A change refused because the user doesn't own the link
+#[derive(Debug)]
+pub struct Forbidden {
+    pub link_id: i64,
+    pub user: Option<String>,
+}
+impl Display for Forbidden {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.user {
+            Some(user) => write!(f, "'{user}' may not change link '{}'", self.link_id),
+            None => write!(f, "anonymous users may not change link '{}'", self.link_id),
+        }
+    }
+}
+impl std::error::Error for Forbidden {}
+
+/// The authenticated user, as asserted by a trusted reverse-proxy header
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    pub user: Option<String>,
+    /// The namespace to resolve sources in before the global one, e.g. the user's team
+    pub namespace: Option<String>,
+    /// Groups the user is a member of, for restricted links
+    pub groups: Vec<String>,
+    /// The anonymous visitor id from the visitor cookie
+    pub visitor: Option<String>,
+}
+impl Identity {
+    /// Who to keep on the same variant of a link: the user, or else the visitor
+    pub fn sticky_key(&self) -> Option<&str> {
+        self.user.as_deref().or(self.visitor.as_deref())
+    }
+
+    /// The user hashed with a salt, to count distinct users in clicks without naming them
+    pub fn user_hash(&self, salt: &str) -> Option<String> {
+        self.user.as_deref().map(|user| {
+            sha256(format!("{salt}:{user}").as_bytes())
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect()
+        })
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Identity {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let user = header(&app_state.identity_header);
+        let namespace = header(&app_state.namespace_header)
+            .map(|namespace| namespace.trim_matches('/').to_string())
+            .filter(|namespace| !namespace.is_empty());
+
+        let groups = header(&app_state.groups_header)
+            .map(|groups| {
+                groups
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|group| !group.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let visitor = parts
+            .headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, value)| *name == VISITOR_COOKIE && !value.is_empty())
+            .map(|(_, value)| value.to_string());
+
+        Ok(Self {
+            user,
+            namespace,
+            groups,
+            visitor,
+        })
+    }
+}
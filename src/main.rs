@@ -1,12 +1,24 @@
+mod cluster;
+mod evaluate;
+mod filter;
+mod forward;
 mod handler;
+mod identity;
 mod model;
 mod query;
 mod route;
 mod schema;
 mod service;
+mod source;
+mod tag;
+mod target;
 mod template;
 
-use std::{env, sync::Arc};
+use std::{
+    env,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use axum::http::{Method, header::CONTENT_TYPE};
 
@@ -20,10 +32,78 @@ use sqlx::{
 };
 
 use route::create_router;
+use schema::{ClusterTopics, CreateClick, PurgeTrash};
+use service::{
+    cluster_topics_job, flush_clicks, purge_trash_job, record_clicks_job, rekey_sources,
+};
+use source::SourcePolicy;
+use target::TargetPolicy;
 use tower_http::cors::{Any, CorsLayer};
 
 pub struct AppState {
     db: SqlitePool,
+    identity_header: String,
+    namespace_header: String,
+    groups_header: String,
+    target_policy: TargetPolicy,
+    source_policy: SourcePolicy,
+    /// Clicks waiting to be written by the next batch
+    pending_clicks: Mutex<Vec<CreateClick>>,
+    /// Salt for hashing users into clicks, which are anonymous without it
+    click_user_salt: Option<String>,
+}
+
+impl AppState {
+    /// Configure the service from the environment, with defaults for anything unset
+    fn from_env(db: SqlitePool) -> Self {
+        Self {
+            db,
+            identity_header: env::var("IDENTITY_HEADER")
+                .unwrap_or_else(|_| "X-Forwarded-User".to_string()),
+            namespace_header: env::var("NAMESPACE_HEADER")
+                .unwrap_or_else(|_| "X-Forwarded-Namespace".to_string()),
+            groups_header: env::var("GROUPS_HEADER")
+                .unwrap_or_else(|_| "X-Forwarded-Groups".to_string()),
+            target_policy: TargetPolicy {
+                schemes: env::var("ALLOWED_SCHEMES")
+                    .map(|schemes| {
+                        schemes
+                            .split(',')
+                            .map(|scheme| scheme.trim().to_lowercase())
+                            .filter(|scheme| !scheme.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_else(|_| TargetPolicy::default().schemes),
+                strip_tracking: env::var("STRIP_TRACKING_PARAMS")
+                    .is_ok_and(|strip| matches!(strip.as_str(), "1" | "true")),
+            },
+            source_policy: {
+                let default = SourcePolicy::default();
+                SourcePolicy {
+                    reserved: default
+                        .reserved
+                        .into_iter()
+                        .chain(list_var("RESERVED_SOURCES"))
+                        .collect(),
+                    punctuation: env::var("SOURCE_PUNCTUATION").unwrap_or(default.punctuation),
+                    min_length: env::var("SOURCE_MIN_LENGTH")
+                        .ok()
+                        .and_then(|len| len.parse().ok())
+                        .unwrap_or(default.min_length),
+                    max_length: env::var("SOURCE_MAX_LENGTH")
+                        .ok()
+                        .and_then(|len| len.parse().ok())
+                        .unwrap_or(default.max_length),
+                    privileged: list_var("PRIVILEGED_NAMESPACES"),
+                    admins: list_var("SOURCE_ADMINS"),
+                }
+            },
+            pending_clicks: Mutex::default(),
+            click_user_salt: env::var("CLICK_USER_SALT")
+                .ok()
+                .filter(|salt| !salt.is_empty()),
+        }
+    }
 }
 
 #[tokio::main]
@@ -31,34 +111,146 @@ async fn main() -> Result<(), Error> {
     dotenv().ok();
     println!("🔎 Go! Crowdsourced Search Service");
 
-    let db = init_db().await?;
+    // Evaluation runs against a fixture, built from the standing data unless one is given, so
+    // that it never touches the live database
+    let args = env::args().collect::<Vec<_>>();
+    let evaluation = match args.as_slice() {
+        [_, command, labels, rest @ ..] if command == "evaluate" => {
+            let k = rest.first().and_then(|k| k.parse().ok()).unwrap_or(10);
+            Some((labels, k))
+        }
+        _ => None,
+    };
+    let filename = if evaluation.is_some() {
+        env::var("EVALUATION_DATABASE_FILENAME").unwrap_or_else(|_| ":memory:".to_string())
+    } else {
+        env::var("DATABASE_FILENAME").expect("DATABASE_FILENAME not set")
+    };
+
+    let db = init_db(&filename).await?;
+    let app_state = Arc::new(AppState::from_env(db));
+
+    if let Some((labels, k)) = evaluation {
+        return evaluate::report(&app_state, labels, k).await;
+    }
+
+    let rekeyed = rekey_sources(&app_state).await?;
+    if rekeyed > 0 {
+        println!("🔑 Rekeyed {rekeyed} link sources");
+    }
 
     let cors = CorsLayer::new()
         .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::PUT])
         .allow_origin(Any)
         .allow_headers([CONTENT_TYPE]);
 
-    let app = create_router(Arc::new(AppState { db })).layer(cors);
+    tokio::spawn(cluster_topics_job(
+        app_state.clone(),
+        ClusterTopics {
+            k: env::var("CLUSTER_COUNT").ok().and_then(|k| k.parse().ok()),
+        },
+        Duration::from_secs(
+            env::var("CLUSTER_INTERVAL")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(3600),
+        ),
+    ));
+    tokio::spawn(purge_trash_job(
+        app_state.clone(),
+        PurgeTrash {
+            retention_days: env::var("TRASH_RETENTION_DAYS")
+                .ok()
+                .and_then(|days| days.parse().ok())
+                .unwrap_or(PurgeTrash::default().retention_days),
+        },
+        Duration::from_secs(
+            env::var("TRASH_PURGE_INTERVAL")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(3600),
+        ),
+    ));
+
+    tokio::spawn(record_clicks_job(
+        app_state.clone(),
+        Duration::from_secs(
+            env::var("CLICK_FLUSH_INTERVAL")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(5),
+        ),
+    ));
+
+    let app = create_router(app_state.clone()).layer(cors);
 
     let listener = TcpListener::bind("0.0.0.0:8080").await.unwrap();
     println!("✅ Server started successfully at 0.0.0.0:8080");
 
     axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap();
 
+    // Write whatever clicks are still queued before exiting
+    flush_clicks(&app_state).await?;
+    println!("👋 Server stopped");
+
     Ok(())
 }
 
-async fn init_db() -> Result<Pool<Sqlite>, Error> {
+/// Ctrl+C, or SIGTERM from a container runtime
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {},
+        () = terminate => {},
+    }
+}
+
+/// A comma-separated environment variable, empty if unset
+fn list_var(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+async fn init_db(filename: &str) -> Result<Pool<Sqlite>, Error> {
     let conn_opts = SqliteConnectOptions::new()
-        .filename(env::var("DATABASE_FILENAME").expect("DATABASE_FILENAME not set"))
+        .filename(filename)
         .extension("extensions/vec")
         .extension("extensions/lembed")
         .extension("extensions/fuzzy")
         .create_if_missing(true);
 
-    let pool_opts = SqlitePoolOptions::new().after_connect(|conn, _meta| {
+    // An in-memory database lives only as long as its one connection
+    let pool_opts = if filename == ":memory:" {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+    } else {
+        SqlitePoolOptions::new()
+    };
+    let pool_opts = pool_opts.after_connect(|conn, _meta| {
         Box::pin(async move {
             println!("🔄 Running initialisations...");
             let initialiser = sqlx::migrate!("./initialisations");
@@ -76,8 +268,28 @@ async fn init_db() -> Result<Pool<Sqlite>, Error> {
 
     let db = pool_opts.connect_with(conn_opts).await?;
 
+    // Each migration runs in a transaction, where `pragma foreign_keys` is a no-op, so rebuilding a
+    // table would cascade its drop to the rows referencing it unless they're off beforehand
     println!("🔄 Running migrations...");
-    sqlx::migrate!("./migrations").run(&db).await?;
+    let mut conn = db.acquire().await?;
+    conn.execute("pragma foreign_keys = off").await?;
+    sqlx::migrate!("./migrations").run(&mut *conn).await?;
+    conn.execute("pragma foreign_keys = on").await?;
+    drop(conn);
 
     Ok(db)
 }
+
+/// The fixture `evaluate` runs against, an in-memory database holding the standing data, or none
+/// where the extensions and embedding model aren't installed, as in CI
+#[cfg(test)]
+async fn test_state() -> Option<Arc<AppState>> {
+    use std::path::Path;
+
+    if !Path::new("extensions").is_dir() || !Path::new("models").is_dir() {
+        eprintln!("⏭️ Skipping, as the database needs extensions/ and models/");
+        return None;
+    }
+    let db = init_db(":memory:").await.expect("fixture database");
+    Some(Arc::new(AppState::from_env(db)))
+}
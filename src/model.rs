@@ -1,24 +1,492 @@
-use chrono::NaiveDateTime;
+use std::fmt::Display;
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use serde_json::Value;
+use sqlx::{FromRow, Type, types::Json};
+
+use crate::schema::{PagingOptions, SearchOptions, SortOptions};
+
+/// HTTP statuses a link may redirect with
+pub const REDIRECT_STATUSES: [u16; 5] = [301, 302, 303, 307, 308];
+
+/// The redirect status of links that don't choose one, See Other
+pub const DEFAULT_REDIRECT_STATUS: u16 = 303;
+
+pub fn default_redirect_status() -> u16 {
+    DEFAULT_REDIRECT_STATUS
+}
+
+/// How far ahead of `valid_until` a link is flagged as expiring
+const EXPIRY_WARNING: TimeDelta = TimeDelta::days(7);
+
+/// The current time, comparable with sqlite's `current_timestamp`
+pub fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+/// A stable bucket in `0..100` for a visitor of a link, using FNV-1a so that it survives restarts
+fn bucket(link_id: i64, visitor: &str) -> i64 {
+    let hash = format!("{link_id}:{visitor}")
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+    (hash % 100) as i64
+}
+
+/// Who can find a link
+#[derive(Debug, Clone, Copy, Default, Type, Deserialize, Serialize, PartialEq, Eq)]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// Resolved and listed for everyone
+    #[default]
+    Public,
+    /// Resolved for everyone, but left out of search results and suggestions
+    Unlisted,
+    /// Resolved and listed only for owners and members of the link's groups
+    Restricted,
+}
+impl Visibility {
+    pub const ALL: [Self; 3] = [Self::Public, Self::Unlisted, Self::Restricted];
+}
+impl Display for Visibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Public => write!(f, "public"),
+            Self::Unlisted => write!(f, "unlisted"),
+            Self::Restricted => write!(f, "restricted"),
+        }
+    }
+}
 
-use crate::schema::{PagingOptions, SearchOptions};
+/// What happens to search parameters and fragments a link doesn't use itself
+#[derive(Debug, Clone, Copy, Default, Type, Deserialize, Serialize, PartialEq, Eq)]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Forwarding {
+    /// Dropped
+    #[default]
+    Off,
+    /// Forwarded, with the target's own parameters and fragment winning conflicts
+    Target,
+    /// Forwarded, with the request's parameters and fragment winning conflicts
+    Request,
+    /// Forwarded, keeping both values of conflicting parameters and the request's fragment
+    Both,
+}
+impl Forwarding {
+    pub const ALL: [Self; 4] = [Self::Off, Self::Target, Self::Request, Self::Both];
+}
+impl Display for Forwarding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Target => write!(f, "target"),
+            Self::Request => write!(f, "request"),
+            Self::Both => write!(f, "both"),
+        }
+    }
+}
 
 #[derive(Debug, FromRow, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Link {
     pub id: i64,
 
-    // created_by: String,
+    pub created_by: Option<String>,
     pub created_at: NaiveDateTime,
-    // modified_by: String,
+    pub modified_by: Option<String>,
     pub modified_at: NaiveDateTime,
+    pub owner: Option<String>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub co_owners: Vec<String>,
+    /// Set for a personal link, visible only to this user
+    #[serde(default)]
+    pub personal_to: Option<String>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// Groups whose members may see a restricted link
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Set while the link is in the trash
+    #[serde(default)]
+    pub deleted_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub deleted_by: Option<String>,
 
     pub source: String,
     pub is_alias: bool,
+    /// Renders its items as a page instead of redirecting, and has no target of its own
+    #[serde(default)]
+    pub is_collection: bool,
+    pub target: String,
+    /// One of `REDIRECT_STATUSES`
+    #[serde(default = "default_redirect_status")]
+    pub redirect_status: u16,
+    #[serde(default)]
+    pub forwarding: Forwarding,
+    pub description: String,
+    #[serde(default)]
+    pub valid_from: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub valid_until: Option<NaiveDateTime>,
+    /// Set while the link is deprecated, in favour of `replaced_by` if given
+    #[serde(default)]
+    pub deprecated_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub replaced_by: Option<String>,
+    #[serde(default)]
+    pub deprecation_message: Option<String>,
+    /// Redirects served, counted as their clicks are written
+    #[serde(default)]
+    pub hits: i64,
+    #[serde(default)]
+    pub last_used_at: Option<NaiveDateTime>,
+
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Future targets, ordered by when they take over
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+    /// Weighted targets splitting traffic with the link's own target
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub variants: Vec<Variant>,
+    /// Sources of the aliases that lead here
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Sources of the aliases passed through to reach this link, when resolved from a search
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub via: Vec<String>,
+    /// Clicks since deprecation, for deprecated links
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    /// Member links of a collection, in order
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub items: Vec<CollectionItem>,
+}
+impl Link {
+    pub fn is_expired(&self) -> bool {
+        self.valid_until.is_some_and(|until| until <= now())
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.valid_from.is_some_and(|from| now() < from)
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.is_expired() && !self.is_pending()
+    }
+
+    pub fn expires_soon(&self) -> bool {
+        self.valid_until
+            .is_some_and(|until| now() < until && until - now() <= EXPIRY_WARNING)
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated_at.is_some()
+    }
+
+    /// The target of the latest schedule to have taken over, or the link's own target
+    pub fn active_target(&self) -> &str {
+        self.schedules
+            .iter()
+            .filter(|schedule| schedule.active_from <= now())
+            .max_by_key(|schedule| schedule.active_from)
+            .map_or(&self.target, |schedule| &schedule.target)
+    }
+
+    /// Whether resolving continues past this link: an alias, unless it is out of its validity
+    /// window or deprecated, for the caller to explain
+    pub fn leads_on(&self) -> bool {
+        self.is_alias && self.is_active() && !self.is_deprecated()
+    }
+
+    /// The percentage of traffic left for the link's own target
+    pub fn primary_weight(&self) -> i64 {
+        100 - self
+            .variants
+            .iter()
+            .map(|variant| variant.weight)
+            .sum::<i64>()
+    }
+
+    /// The variant a visitor is sticky to, or none for the link's own target
+    pub fn pick_variant(&self, visitor: &str) -> Option<&Variant> {
+        let bucket = bucket(self.id, visitor);
+        let mut threshold = 0;
+        self.variants.iter().find(|variant| {
+            threshold += variant.weight;
+            bucket < threshold
+        })
+    }
+}
+
+/// A member of a collection, with the member link's details
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CollectionItem {
+    pub id: i64,
+    pub collection_id: i64,
+    pub link_id: i64,
+    pub position: i64,
+    pub note: String,
+
+    pub source: String,
     pub target: String,
     pub description: String,
 }
 
+/// A namespace and how many links are in it or below it
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Namespace {
+    pub name: String,
+    pub links: i64,
+}
+
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Usage {
+    pub link_id: i64,
+    pub uses: i64,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+/// Click counts for a link's preview
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Stats {
+    pub clicks: i64,
+    /// Clicks in the last 30 days
+    pub recent_clicks: i64,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Schedule {
+    pub id: i64,
+
+    pub created_at: NaiveDateTime,
+
+    pub link_id: i64,
+    pub target: String,
+    pub active_from: NaiveDateTime,
+}
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Variant {
+    pub id: i64,
+
+    pub created_at: NaiveDateTime,
+
+    pub link_id: i64,
+    pub target: String,
+    /// Percentage of traffic
+    pub weight: i64,
+    /// Clicks served this variant
+    pub clicks: i64,
+}
+
+impl Schedule {
+    pub fn is_active(&self) -> bool {
+        self.active_from <= now()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FieldDiff {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+impl FieldDiff {
+    fn display(value: &Value) -> String {
+        match value {
+            Value::Null => "".to_string(),
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        }
+    }
+    pub fn before_text(&self) -> String {
+        Self::display(&self.before)
+    }
+    pub fn after_text(&self) -> String {
+        Self::display(&self.after)
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Revision {
+    pub id: i64,
+
+    pub revised_by: Option<String>,
+    pub revised_at: NaiveDateTime,
+
+    pub link_id: i64,
+    pub action: String,
+    /// Snapshot of the link before this revision, absent on creation
+    pub before: Option<Json<Value>>,
+    /// Snapshot of the link after this revision, absent on deletion
+    pub after: Option<Json<Value>>,
+}
+impl Revision {
+    const FIELDS: [&str; 14] = [
+        "source",
+        "is_alias",
+        "target",
+        "description",
+        "owner",
+        "co_owners",
+        "valid_from",
+        "valid_until",
+        "deprecated_at",
+        "replaced_by",
+        "deprecation_message",
+        "visibility",
+        "redirect_status",
+        "forwarding",
+    ];
+
+    pub fn diffs(&self) -> Vec<FieldDiff> {
+        let field = |snapshot: &Option<Json<Value>>, name: &str| {
+            snapshot
+                .as_ref()
+                .and_then(|Json(snapshot)| snapshot.get(name))
+                .cloned()
+                .unwrap_or(Value::Null)
+        };
+        Self::FIELDS
+            .iter()
+            .map(|&name| FieldDiff {
+                field: name.to_string(),
+                before: field(&self.before, name),
+                after: field(&self.after, name),
+            })
+            .filter(|diff| diff.before != diff.after)
+            .collect()
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Topic {
+    pub id: i64,
+
+    pub created_at: NaiveDateTime,
+
+    pub label: String,
+    pub size: i64,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Tag {
+    pub id: i64,
+
+    pub created_at: NaiveDateTime,
+
+    pub name: String,
+    pub size: i64,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Synonym {
+    pub id: i64,
+
+    pub created_at: NaiveDateTime,
+
+    pub term: String,
+    pub synonym: String,
+}
+impl Synonym {
+    /// The query itself, followed by each variant with one or more synonymous phrases
+    /// substituted, fewest substitutions first; phrases match whole words, ignoring case
+    pub fn expand(query: &str, synonyms: &[Synonym]) -> Vec<String> {
+        let words = query.split_whitespace().collect::<Vec<_>>();
+
+        // Every run of words matching a term or synonym, as its start, length and replacement
+        let mut matches = vec![];
+        let pairs = synonyms.iter().flat_map(|Synonym { term, synonym, .. }| {
+            [
+                (term.as_str(), synonym.as_str()),
+                (synonym.as_str(), term.as_str()),
+            ]
+        });
+        for (from, to) in pairs {
+            let phrase = from.split_whitespace().collect::<Vec<_>>();
+            if phrase.is_empty() || phrase.len() > words.len() {
+                continue;
+            }
+            for start in 0..=(words.len() - phrase.len()) {
+                let window = &words[start..start + phrase.len()];
+                if window
+                    .iter()
+                    .zip(&phrase)
+                    .all(|(word, part)| word.eq_ignore_ascii_case(part))
+                {
+                    matches.push((start, phrase.len(), to));
+                }
+            }
+        }
+
+        let mut substituted = vec![];
+        substitute(&words, &matches, 0, &mut vec![], 0, &mut substituted);
+        substituted.sort_by_key(|&(substitutions, _)| substitutions);
+
+        let mut expansions = vec![query.to_string()];
+        for (_, expansion) in substituted {
+            if !expansions.contains(&expansion) {
+                expansions.push(expansion);
+            }
+        }
+        expansions
+    }
+}
+
+/// Most rewrites of a query to search for, as substitutions multiply
+const MAX_EXPANSIONS: usize = 32;
+
+/// Collect every way of rewriting `words` from `at` onwards with non-overlapping `matches`,
+/// along with how many substitutions each took
+fn substitute<'a>(
+    words: &[&'a str],
+    matches: &[(usize, usize, &'a str)],
+    at: usize,
+    rewritten: &mut Vec<&'a str>,
+    substitutions: usize,
+    expansions: &mut Vec<(usize, String)>,
+) {
+    if expansions.len() >= MAX_EXPANSIONS {
+        return;
+    }
+    if at == words.len() {
+        if substitutions > 0 {
+            expansions.push((substitutions, rewritten.join(" ")));
+        }
+        return;
+    }
+
+    rewritten.push(words[at]);
+    substitute(words, matches, at + 1, rewritten, substitutions, expansions);
+    rewritten.pop();
+
+    for &(_, len, to) in matches.iter().filter(|&&(start, _, _)| start == at) {
+        rewritten.push(to);
+        substitute(
+            words,
+            matches,
+            at + len,
+            rewritten,
+            substitutions + 1,
+            expansions,
+        );
+        rewritten.pop();
+    }
+}
+
 #[derive(Debug, FromRow, Deserialize, Serialize, Default)]
 pub struct Paging {
     pub page: u64,
@@ -48,6 +516,11 @@ impl Paging {
             target: target.to_string(),
         }
     }
+    /// Keep an ordering other than relevance across pages
+    pub fn sorted(mut self, sort: &SortOptions) -> Self {
+        self.query.push_str(&sort.as_query());
+        self
+    }
     pub fn full_query(&self) -> String {
         format!(
             "{}{}",
@@ -60,3 +533,171 @@ impl Paging {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synonyms(pairs: &[(&str, &str)]) -> Vec<Synonym> {
+        pairs
+            .iter()
+            .enumerate()
+            .map(|(id, (term, synonym))| Synonym {
+                id: id as i64,
+                created_at: NaiveDateTime::default(),
+                term: term.to_string(),
+                synonym: synonym.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn expand_swaps_both_ways() {
+        let synonyms = synonyms(&[("prod", "production")]);
+        assert_eq!(
+            Synonym::expand("prod logs", &synonyms),
+            vec!["prod logs", "production logs"]
+        );
+        assert_eq!(
+            Synonym::expand("Production logs", &synonyms),
+            vec!["Production logs", "prod logs"]
+        );
+    }
+
+    #[test]
+    fn expand_matches_whole_words_only() {
+        let synonyms = synonyms(&[("prod", "production")]);
+        assert_eq!(Synonym::expand("products", &synonyms), vec!["products"]);
+    }
+
+    #[test]
+    fn expand_multi_word_phrases() {
+        let synonyms = synonyms(&[("k8s", "kubernetes cluster")]);
+        assert_eq!(
+            Synonym::expand("K8s dashboard", &synonyms),
+            vec!["K8s dashboard", "kubernetes cluster dashboard"]
+        );
+        assert_eq!(
+            Synonym::expand("kubernetes  cluster logs", &synonyms),
+            vec!["kubernetes  cluster logs", "k8s logs"]
+        );
+        assert_eq!(
+            Synonym::expand("kubernetes logs", &synonyms),
+            vec!["kubernetes logs"]
+        );
+    }
+
+    #[test]
+    fn expand_combines_phrases() {
+        let synonyms = synonyms(&[("k8s", "kubernetes cluster"), ("prod", "production")]);
+        assert_eq!(
+            Synonym::expand("k8s prod", &synonyms),
+            vec![
+                "k8s prod",
+                "k8s production",
+                "kubernetes cluster prod",
+                "kubernetes cluster production",
+            ]
+        );
+    }
+
+    #[test]
+    fn expand_overlapping_phrases_apart() {
+        let synonyms = synonyms(&[("new york", "nyc"), ("york", "eboracum")]);
+        assert_eq!(
+            Synonym::expand("new york times", &synonyms),
+            vec!["new york times", "new eboracum times", "nyc times"]
+        );
+    }
+
+    #[test]
+    fn expand_is_bounded() {
+        let synonyms = synonyms(&[("a", "b"), ("a", "c")]);
+        let query = ["a"; 8].join(" ");
+        let expansions = Synonym::expand(&query, &synonyms);
+        assert_eq!(expansions[0], query);
+        assert_eq!(expansions.len(), MAX_EXPANSIONS + 1);
+    }
+
+    fn link_with_variants(weights: &[i64]) -> Link {
+        let variants = weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| {
+                serde_json::json!({
+                    "id": i + 1,
+                    "created_at": "2026-01-01T00:00:00",
+                    "link_id": 1,
+                    "target": format!("https://example.com/{}", i + 1),
+                    "weight": weight,
+                    "clicks": 0,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "created_by": null,
+            "created_at": "2026-01-01T00:00:00",
+            "modified_by": null,
+            "modified_at": "2026-01-01T00:00:00",
+            "owner": null,
+            "source": "landing",
+            "is_alias": false,
+            "target": "https://example.com/",
+            "description": "",
+            "variants": variants,
+        }))
+        .unwrap()
+    }
+
+    /// How many of 10000 visitors land on each variant, with the link's own target last
+    fn spread(link: &Link) -> Vec<usize> {
+        let mut counts = vec![0; link.variants.len() + 1];
+        for visitor in 0..10000 {
+            let picked = link.pick_variant(&format!("visitor-{visitor}"));
+            let index = picked.map_or(link.variants.len(), |variant| variant.id as usize - 1);
+            counts[index] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn variants_split_by_weight() {
+        let link = link_with_variants(&[20, 50]);
+        assert_eq!(link.primary_weight(), 30);
+        for (count, weight) in spread(&link).into_iter().zip([20, 50, 30]) {
+            let share = count as f64 / 100.0;
+            assert!((share - weight as f64).abs() < 3.0, "{share}% vs {weight}%");
+        }
+    }
+
+    #[test]
+    fn variants_are_sticky() {
+        let link = link_with_variants(&[50]);
+        for visitor in ["ada", "grace", "visitor-1", ""] {
+            let first = link.pick_variant(visitor).map(|variant| variant.id);
+            for _ in 0..10 {
+                assert_eq!(link.pick_variant(visitor).map(|variant| variant.id), first);
+            }
+        }
+        assert_eq!(bucket(1, "ada"), bucket(1, "ada"));
+    }
+
+    #[test]
+    fn buckets_differ_between_links() {
+        let differing = (0..100)
+            .filter(|visitor| {
+                let visitor = format!("visitor-{visitor}");
+                bucket(1, &visitor) != bucket(2, &visitor)
+            })
+            .count();
+        assert!(differing > 50);
+    }
+
+    #[test]
+    fn no_weight_falls_back_to_target() {
+        assert_eq!(spread(&link_with_variants(&[])), vec![10000]);
+        assert_eq!(spread(&link_with_variants(&[0, 0])), vec![0, 0, 10000]);
+        assert_eq!(spread(&link_with_variants(&[100])), vec![10000, 0]);
+    }
+}
@@ -1,12 +1,32 @@
 use crate::{
-    model::Link,
-    schema::{CreateLink, DeleteLink, FindLink, GetLink, QueryLinks, SearchMethod, UpdateLink},
+    identity::Identity,
+    model::{
+        CollectionItem, DEFAULT_REDIRECT_STATUS, Link, Revision, Schedule, Stats, Synonym, Tag,
+        Topic, Usage, Variant,
+    },
+    schema::{
+        AddCoOwner, AddCollectionItem, AddLinkGroup, AddSchedule, AddVariant, AssignTopic,
+        ClearLinkGroups, ClearLinkTags, ClearTopics, CreateClick, CreateLink, CreateRevision,
+        CreateSynonym, CreateTag, CreateTopic, DeleteLink, DeleteSynonym, DeleteTag, DeprecateLink,
+        FindLink, FindLinksByTarget, GetLink, GetRevision, GetSourceKeyVersion, GetStats,
+        GetSynonym, GetTag, GetTopic, ListCoOwners, ListCollectionItems, ListDependentAliases,
+        ListEmbeddings, ListLinkAliases, ListLinkGroups, ListLinkTags, ListNamespaces,
+        ListPersonalLinks, ListRevisions, ListSchedules, ListSourceKeys, ListSynonyms, ListTags,
+        ListTopics, ListTrash, ListUsage, ListVariants, MatchSynonyms, PurgeTrash, QueryLinks,
+        QueryTopicLinks, ReleaseSource, RemoveCoOwner, RemoveCollectionItem, RemoveSchedule,
+        RemoveVariant, RenameLink, ReorderCollectionItems, RestoreLink, RetargetAlias,
+        SearchMethod, SetSourceKey, SetSourceKeyVersion, SetVisibility, SortMethod, SortOptions,
+        SortOrder, TagLink, TouchLink, TransferLink, UndeprecateLink, UntagLink, UntrashLink,
+        UpdateCollectionItem, UpdateLink, UpdateSynonym, UpdateTag,
+    },
+    source, target,
 };
 
 use sqlx::{
     Sqlite,
     query::{Query, QueryAs, QueryScalar},
     sqlite::SqliteArguments,
+    types::Json,
 };
 use static_str_ops::static_format;
 
@@ -24,105 +44,1250 @@ const SEMANTIC_H: &str = r#"with
         -- and k = 100
     )
 "#;
+/// Usage score per link in `[0, 1.75)`, blending click count, click recency and
+/// click-through from the current query; requires a preceding `queries` CTE
+const POPULARITY_H: &str = r#"
+    popularity as (
+        select
+            clicks.link_id,
+            0.5 * count(*) / (count(*) + 5.0)
+            + 0.25 / (1.0 + (julianday('now') - julianday(max(clicks.clicked_at))) / 7.0)
+            + 1.0 * sum(clicks.query = lower(trim(queries.query)))
+                / (sum(clicks.query = lower(trim(queries.query))) + 1.0)
+            as boost
+        from clicks, queries
+        group by clicks.link_id
+    )
+"#;
+/// Synonymous rewrites of the query, bound as a JSON array
+const EXPANSIONS_H: &str = r#"
+    expansions as (select value as query from json_each(?))
+"#;
+/// Links the user may see: global links and their own personal links, and restricted links only
+/// if they own them or are in one of their groups; bound with `visible_binds`
+const VISIBLE: &str = r#"
+    (links.personal_to is null or links.personal_to = ?)
+    and (
+        links.visibility != 'restricted'
+        or links.owner = ?
+        or exists (
+            select 1 from link_owners
+            where link_owners.link_id = links.id and link_owners.owner = ?
+        )
+        or exists (
+            select 1 from link_groups
+            where link_groups.link_id = links.id
+            and link_groups.name in (select value from json_each(?))
+        )
+    )
+"#;
+/// Links that may appear in search results and suggestions, as well as being `VISIBLE`
+const LISTED: &str = "links.visibility != 'unlisted'";
+fn visible_binds(identity: &Identity) -> [Option<String>; 4] {
+    [
+        identity.user.clone(),
+        identity.user.clone(),
+        identity.user.clone(),
+        serde_json::to_string(&identity.groups).ok(),
+    ]
+}
 impl QueryLinks {
-    pub fn as_semantic_count(&self) -> QueryScalar<'_, Sqlite, i64, SqliteArguments<'_>> {
-        sqlx::query_scalar::<_, i64>(static_format!(
-            r#"{SEMANTIC_H}
-            select count(*) from matches;
-            "#,
-        ))
-        .bind(&self.search.query)
+    fn expansions_json(&self) -> String {
+        let expansions = if self.expansions.is_empty() {
+            std::slice::from_ref(&self.filter.text)
+        } else {
+            &self.expansions
+        };
+        serde_json::to_string(expansions).unwrap_or_default()
     }
 
-    fn as_semantic_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
-        sqlx::query_as::<_, Link>(static_format!(
-            r#"
-            {SEMANTIC_H}
-            select * from matches
-            left join links on matches.rowid = links.id
-            order by matches.distance asc
-            limit ? offset ?;
-            "#
-        ))
-        .bind(&self.search.query)
-        .bind(self.paging.limit as i64)
-        .bind(self.paging.offset() as i64)
+    /// The SQL for `as_query`, built per search as the filters and sort come from the user
+    pub fn as_sql(&self) -> String {
+        let (filters, _) = self.filter.as_sql(&self.identity);
+        let sort = self.sort.as_order();
+        match self.search.method {
+            SearchMethod::Semantic if self.filter.text.is_empty() => format!(
+                r#"
+                with
+                    queries as (select ? as query),
+                    {POPULARITY_H}
+                select links.* from links
+                left join popularity on popularity.link_id = links.id
+                where links.deleted_at is null
+                and {VISIBLE}
+                and {LISTED}
+                and {filters}
+                order by {sort} coalesce(popularity.boost, 0) desc, links.source asc
+                limit ? offset ?;
+                "#
+            ),
+            SearchMethod::Semantic => format!(
+                r#"
+                {SEMANTIC_H},
+                {POPULARITY_H}
+                select * from matches
+                left join links on matches.rowid = links.id
+                left join popularity on popularity.link_id = links.id
+                where links.deleted_at is null
+                and {VISIBLE}
+                and {LISTED}
+                and {filters}
+                order by {sort} matches.distance - ? * coalesce(popularity.boost, 0) asc
+                limit ? offset ?;
+                "#
+            ),
+            SearchMethod::DamerauLevenshtein => format!(
+                r#"
+                with
+                    queries as (select ? as query),
+                    {EXPANSIONS_H},
+                    {POPULARITY_H}
+                select links.* from links
+                left join popularity on popularity.link_id = links.id
+                where links.deleted_at is null
+                and {VISIBLE}
+                and {LISTED}
+                and {filters}
+                order by {sort} (
+                    select min(fuzzy_damlev(links.source, expansions.query)) from expansions
+                ) - ? * coalesce(popularity.boost, 0) asc
+                limit ? offset ?;
+                "#
+            ),
+        }
+    }
+
+    pub fn as_query<'q>(&'q self, sql: &'q str) -> QueryAs<'q, Sqlite, Link, SqliteArguments<'q>> {
+        let (_, binds) = self.filter.as_sql(&self.identity);
+        let mut query = sqlx::query_as::<_, Link>(sql).bind(&self.filter.text);
+        if self.search.method == SearchMethod::DamerauLevenshtein {
+            query = query.bind(self.expansions_json());
+        }
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        for bind in binds {
+            query = query.bind(bind);
+        }
+        // Only ranked searches weigh in popularity against relevance
+        if self.search.method != SearchMethod::Semantic || !self.filter.text.is_empty() {
+            query = query.bind(self.search.boost_weight());
+        }
+        query
+            .bind(self.paging.limit as i64)
+            .bind(self.paging.offset() as i64)
     }
 
-    fn as_damlev_count(&self) -> QueryScalar<'_, Sqlite, i64, SqliteArguments<'_>> {
-        sqlx::query_scalar(static_format!(
+    /// The SQL for `as_count`, built per search like `as_sql`
+    pub fn as_count_sql(&self) -> String {
+        let (filters, _) = self.filter.as_sql(&self.identity);
+        match self.search.method {
+            SearchMethod::Semantic if !self.filter.text.is_empty() => format!(
+                r#"
+                {SEMANTIC_H}
+                select count(*) from matches
+                left join links on matches.rowid = links.id
+                where links.deleted_at is null
+                and {VISIBLE}
+                and {LISTED}
+                and {filters};
+                "#
+            ),
+            _ => format!(
+                r#"
+                select count(*) from links
+                where links.deleted_at is null
+                and {VISIBLE}
+                and {LISTED}
+                and {filters}
+                "#
+            ),
+        }
+    }
+
+    pub fn as_count<'q>(
+        &'q self,
+        sql: &'q str,
+    ) -> QueryScalar<'q, Sqlite, i64, SqliteArguments<'q>> {
+        let (_, binds) = self.filter.as_sql(&self.identity);
+        let mut query = sqlx::query_scalar::<_, i64>(sql);
+        if self.search.method == SearchMethod::Semantic && !self.filter.text.is_empty() {
+            query = query.bind(&self.filter.text);
+        }
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        for bind in binds {
+            query = query.bind(bind);
+        }
+        query
+    }
+}
+
+impl SortOptions {
+    /// Leading `order by` terms, with relevance breaking ties
+    fn as_order(&self) -> String {
+        let column = match self.sort_by {
+            SortMethod::Relevance => return String::new(),
+            SortMethod::Alphabetical => "links.source",
+            SortMethod::Created => "links.created_at",
+            SortMethod::Updated => "links.modified_at",
+            SortMethod::Hits => "links.hits",
+            SortMethod::LastUsed => "links.last_used_at",
+        };
+        let order = match self.order {
+            SortOrder::Ascending => "asc",
+            SortOrder::Descending => "desc",
+        };
+        format!("{column} {order},")
+    }
+}
+
+impl CreateClick {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(
             r#"
-            select count(*) from links
+            insert into clicks (link_id, query, variant_id, alias_chain, user_hash, clicked_at)
+            values (?, lower(trim(?)), ?, ?, ?, ?)
             "#,
-        ))
-        .bind(&self.search.query)
+        )
+        .bind(self.link_id)
+        .bind(&self.query)
+        .bind(self.variant_id)
+        .bind(serde_json::to_string(&self.alias_chain).unwrap_or_default())
+        .bind(&self.user_hash)
+        .bind(self.clicked_at)
     }
+}
 
-    fn as_damlev_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
-        sqlx::query_as::<_, Link>(static_format!(
+impl GetLink {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
+        let mut query = sqlx::query_as::<_, Link>(static_format!(
             r#"
             select * from links
-            order by fuzzy_damlev(links.source, ?) asc
-            limit ? offset ?;
+            where links.id = ? and links.deleted_at is null
+            and {VISIBLE}
             "#,
         ))
-        .bind(&self.search.query)
-        .bind(self.paging.limit as i64)
-        .bind(self.paging.offset() as i64)
+        .bind(self.id);
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        query
+    }
+
+    /// Regardless of who is asking or the trash, to read back a link the user just changed and
+    /// may no longer be able to see
+    pub fn as_unscoped_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Link>(r#"select * from links where id = ?"#).bind(self.id)
     }
+}
 
+impl FindLink {
     pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
-        match self.search.method {
-            SearchMethod::Semantic => self.as_semantic_query(),
-            SearchMethod::DamerauLevenshtein => self.as_damlev_query(),
+        let mut query = sqlx::query_as::<_, Link>(static_format!(
+            r#"
+            select * from links
+            where links.source_key = ? and links.deleted_at is null
+            and {VISIBLE}
+            order by links.personal_to is null
+            limit 1
+            "#,
+        ))
+        .bind(source::key(&self.source));
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
         }
+        query
     }
+}
 
-    pub fn as_count(&self) -> QueryScalar<'_, Sqlite, i64, SqliteArguments<'_>> {
-        match self.search.method {
-            SearchMethod::Semantic => self.as_semantic_count(),
-            SearchMethod::DamerauLevenshtein => self.as_damlev_count(),
-        }
+impl ListSourceKeys {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, (i64, String, String), SqliteArguments<'_>> {
+        sqlx::query_as(r#"select id, source, coalesce(source_key, '') from links"#)
     }
 }
 
-impl GetLink {
-    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
-        sqlx::query_as::<_, Link>(r#"select * from links where id = ?"#).bind(self.id)
+impl GetSourceKeyVersion {
+    pub fn as_query(&self) -> QueryScalar<'_, Sqlite, i64, SqliteArguments<'_>> {
+        sqlx::query_scalar(
+            r#"select coalesce((select cast(value as integer) from settings where name = 'source_key_version'), 0)"#,
+        )
     }
 }
 
-impl FindLink {
+impl SetSourceKeyVersion {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(
+            r#"
+            insert into settings (name, value) values ('source_key_version', ?)
+            on conflict (name) do update set value = excluded.value
+            "#,
+        )
+        .bind(self.version.to_string())
+    }
+}
+
+impl SetSourceKey {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"update links set source_key = ? where id = ?"#)
+            .bind(&self.source_key)
+            .bind(self.id)
+    }
+}
+
+impl FindLinksByTarget {
+    /// Candidates sharing the URL's host, to be compared canonically
     pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
-        sqlx::query_as::<_, Link>(r#"select * from links where source = ?"#).bind(&self.source)
+        let host = target::host(&self.url).unwrap_or_else(|| self.url.trim().to_lowercase());
+        let mut query = sqlx::query_as::<_, Link>(static_format!(
+            r#"
+            select * from links
+            where not links.is_alias and links.deleted_at is null
+            and instr(lower(links.target), ?) > 0
+            and {VISIBLE}
+            and {LISTED}
+            order by links.source asc
+            "#,
+        ))
+        .bind(host);
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        query
+    }
+}
+
+impl ListLinkAliases {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, (String, String), SqliteArguments<'_>> {
+        sqlx::query_as(
+            r#"
+            select target, source from links
+            where is_alias and deleted_at is null and personal_to is null
+            and visibility = 'public'
+            order by source asc
+            "#,
+        )
     }
 }
 
 impl CreateLink {
-    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
+    pub fn as_query<'q>(
+        &'q self,
+        identity: &'q Identity,
+    ) -> QueryAs<'q, Sqlite, Link, SqliteArguments<'q>> {
         sqlx::query_as::<_, Link>(
-            r#"insert into links (source, is_alias, target) values (?, ?, ?) returning *"#,
+            r#"
+            insert into links (
+                source, source_key, is_alias, is_collection, target, redirect_status, forwarding,
+                description, valid_from, valid_until, created_by, modified_by, owner, personal_to
+            )
+            values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            returning *
+            "#,
         )
         .bind(&self.source)
+        .bind(source::key(&self.source))
         .bind(self.is_alias)
+        .bind(self.is_collection)
         .bind(&self.target)
+        .bind(self.redirect_status.unwrap_or(DEFAULT_REDIRECT_STATUS))
+        .bind(self.forwarding)
+        .bind(&self.description)
+        .bind(self.valid_from)
+        .bind(self.valid_until)
+        .bind(&identity.user)
+        .bind(&identity.user)
+        .bind(&identity.user)
+        .bind(self.personal.then_some(&identity.user))
     }
 }
 
 impl UpdateLink {
-    pub fn as_query(&self, id: i64) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
+    pub fn as_query<'q>(
+        &'q self,
+        id: i64,
+        identity: &'q Identity,
+    ) -> QueryAs<'q, Sqlite, Link, SqliteArguments<'q>> {
         sqlx::query_as(
-            r#"update links set source = ?, is_alias = ?, target = ? where id = ? returning *"#,
+            r#"
+            update links
+            set source = ?, source_key = ?, is_alias = ?, target = ?,
+                redirect_status = coalesce(?, redirect_status),
+                forwarding = coalesce(?, forwarding),
+                description = coalesce(?, description),
+                valid_from = case when ? then ? else valid_from end,
+                valid_until = case when ? then ? else valid_until end,
+                modified_by = ?, modified_at = current_timestamp
+            where id = ?
+            returning *
+            "#,
         )
         .bind(&self.source)
+        .bind(source::key(&self.source))
         .bind(self.is_alias)
         .bind(&self.target)
+        .bind(self.redirect_status)
+        .bind(self.forwarding)
+        .bind(&self.description)
+        .bind(self.valid_from.is_some())
+        .bind(self.valid_from.flatten())
+        .bind(self.valid_until.is_some())
+        .bind(self.valid_until.flatten())
+        .bind(&identity.user)
+        .bind(id)
+    }
+}
+
+impl TransferLink {
+    pub fn as_query<'q>(
+        &'q self,
+        id: i64,
+        identity: &'q Identity,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        sqlx::query(
+            r#"
+            update links
+            set owner = ?, modified_by = ?, modified_at = current_timestamp
+            where id = ?
+            "#,
+        )
+        .bind(&self.owner)
+        .bind(&identity.user)
+        .bind(id)
+    }
+}
+
+impl ListLinkGroups {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, (i64, String), SqliteArguments<'_>> {
+        sqlx::query_as(
+            r#"
+            select link_id, name from link_groups
+            where link_id in (select value from json_each(?))
+            order by name asc
+            "#,
+        )
+        .bind(serde_json::to_string(&self.link_ids).unwrap_or_default())
+    }
+}
+
+impl ClearLinkGroups {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"delete from link_groups where link_id = ?"#).bind(self.link_id)
+    }
+}
+
+impl AddLinkGroup {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"insert or ignore into link_groups (link_id, name) values (?, ?)"#)
+            .bind(self.link_id)
+            .bind(&self.name)
+    }
+}
+
+impl SetVisibility {
+    pub fn as_query<'q>(
+        &'q self,
+        id: i64,
+        identity: &'q Identity,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        sqlx::query(
+            r#"
+            update links
+            set visibility = ?, modified_by = ?, modified_at = current_timestamp
+            where id = ?
+            "#,
+        )
+        .bind(self.visibility)
+        .bind(&identity.user)
         .bind(id)
     }
 }
 
+impl ListCoOwners {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, (i64, String), SqliteArguments<'_>> {
+        sqlx::query_as(
+            r#"
+            select link_id, owner from link_owners
+            where link_id in (select value from json_each(?))
+            order by owner asc
+            "#,
+        )
+        .bind(serde_json::to_string(&self.link_ids).unwrap_or_default())
+    }
+}
+
+impl DeprecateLink {
+    pub fn as_query<'q>(
+        &'q self,
+        id: i64,
+        identity: &'q Identity,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        sqlx::query(
+            r#"
+            update links
+            set deprecated_at = coalesce(deprecated_at, current_timestamp), replaced_by = ?,
+                deprecation_message = ?, modified_by = ?, modified_at = current_timestamp
+            where id = ?
+            "#,
+        )
+        .bind(&self.replaced_by)
+        .bind(&self.message)
+        .bind(&identity.user)
+        .bind(id)
+    }
+}
+
+impl UndeprecateLink {
+    pub fn as_query<'q>(
+        &'q self,
+        id: i64,
+        identity: &'q Identity,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        sqlx::query(
+            r#"
+            update links
+            set deprecated_at = null, replaced_by = null, deprecation_message = null,
+                modified_by = ?, modified_at = current_timestamp
+            where id = ?
+            "#,
+        )
+        .bind(&identity.user)
+        .bind(id)
+    }
+}
+
+impl TouchLink {
+    pub fn as_query<'q>(
+        &'q self,
+        identity: &'q Identity,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        sqlx::query(
+            r#"update links set modified_by = ?, modified_at = current_timestamp where id = ?"#,
+        )
+        .bind(&identity.user)
+        .bind(self.id)
+    }
+}
+
+impl ListUsage {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Usage, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Usage>(
+            r#"
+            select links.id as link_id, count(clicks.id) as uses, max(clicks.clicked_at) as last_used_at
+            from links
+            left join clicks on clicks.link_id = links.id and clicks.clicked_at >= links.deprecated_at
+            where links.id in (select value from json_each(?)) and links.deprecated_at is not null
+            group by links.id
+            "#,
+        )
+        .bind(serde_json::to_string(&self.link_ids).unwrap_or_default())
+    }
+}
+
+impl ListSchedules {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Schedule, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Schedule>(
+            r#"
+            select * from link_schedules
+            where link_id in (select value from json_each(?))
+            order by active_from asc
+            "#,
+        )
+        .bind(serde_json::to_string(&self.link_ids).unwrap_or_default())
+    }
+}
+
+impl AddSchedule {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"insert into link_schedules (link_id, target, active_from) values (?, ?, ?)"#)
+            .bind(self.link_id)
+            .bind(&self.target)
+            .bind(self.active_from)
+    }
+}
+
+impl RemoveSchedule {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"delete from link_schedules where link_id = ? and id = ?"#)
+            .bind(self.link_id)
+            .bind(self.id)
+    }
+}
+
+impl GetStats {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Stats, SqliteArguments<'_>> {
+        let mut query = sqlx::query_as::<_, Stats>(static_format!(
+            r#"
+            select
+                count(clicks.id) as clicks,
+                coalesce(sum(clicks.clicked_at >= datetime('now', '-30 days')), 0) as recent_clicks,
+                max(clicks.clicked_at) as last_used_at
+            from links
+            left join clicks on clicks.link_id = links.id
+            where links.id = ?
+            and {VISIBLE}
+            group by links.id
+            "#,
+        ))
+        .bind(self.link_id);
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        query
+    }
+}
+
+impl ListVariants {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Variant, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Variant>(
+            r#"
+            select
+                link_variants.*,
+                (select count(*) from clicks where clicks.variant_id = link_variants.id) as clicks
+            from link_variants
+            where link_id in (select value from json_each(?))
+            order by id asc
+            "#,
+        )
+        .bind(serde_json::to_string(&self.link_ids).unwrap_or_default())
+    }
+}
+
+impl AddVariant {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"insert into link_variants (link_id, target, weight) values (?, ?, ?)"#)
+            .bind(self.link_id)
+            .bind(&self.target)
+            .bind(self.weight)
+    }
+}
+
+impl RemoveVariant {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"delete from link_variants where link_id = ? and id = ?"#)
+            .bind(self.link_id)
+            .bind(self.id)
+    }
+}
+
+impl ListCollectionItems {
+    /// Members hidden from some users are left out, as collections are shared
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, CollectionItem, SqliteArguments<'_>> {
+        sqlx::query_as::<_, CollectionItem>(
+            r#"
+            select
+                collection_items.id, collection_items.collection_id, collection_items.link_id,
+                collection_items.position, collection_items.note,
+                links.source, links.target, links.description
+            from collection_items
+            join links on links.id = collection_items.link_id
+            where collection_items.collection_id in (select value from json_each(?))
+            and links.deleted_at is null and links.personal_to is null
+            and links.visibility != 'restricted'
+            order by collection_items.position asc, collection_items.id asc
+            "#,
+        )
+        .bind(serde_json::to_string(&self.link_ids).unwrap_or_default())
+    }
+}
+
+impl AddCollectionItem {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(
+            r#"
+            insert into collection_items (collection_id, link_id, position, note)
+            values (
+                ?, ?,
+                (select coalesce(max(position), 0) + 1 from collection_items where collection_id = ?),
+                ?
+            )
+            "#,
+        )
+        .bind(self.collection_id)
+        .bind(self.link_id)
+        .bind(self.collection_id)
+        .bind(&self.note)
+    }
+}
+
+impl UpdateCollectionItem {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"update collection_items set note = ? where collection_id = ? and id = ?"#)
+            .bind(&self.note)
+            .bind(self.collection_id)
+            .bind(self.id)
+    }
+}
+
+impl RemoveCollectionItem {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"delete from collection_items where collection_id = ? and id = ?"#)
+            .bind(self.collection_id)
+            .bind(self.id)
+    }
+}
+
+impl ReorderCollectionItems {
+    /// Positions follow the order of `item_ids`; items left out keep theirs
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        let item_ids = serde_json::to_string(&self.item_ids).unwrap_or_default();
+        sqlx::query(
+            r#"
+            update collection_items
+            set position = (select key from json_each(?) where value = collection_items.id)
+            where collection_id = ? and id in (select value from json_each(?))
+            "#,
+        )
+        .bind(item_ids.clone())
+        .bind(self.collection_id)
+        .bind(item_ids)
+    }
+}
+
+impl AddCoOwner {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"insert or ignore into link_owners (link_id, owner) values (?, ?)"#)
+            .bind(self.link_id)
+            .bind(&self.owner)
+    }
+}
+
+impl RemoveCoOwner {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"delete from link_owners where link_id = ? and owner = ?"#)
+            .bind(self.link_id)
+            .bind(&self.owner)
+    }
+}
+
 impl DeleteLink {
+    pub fn as_query<'q>(
+        &'q self,
+        identity: &'q Identity,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        sqlx::query(
+            r#"
+            update links
+            set deleted_at = current_timestamp, deleted_by = ?
+            where id = ? and deleted_at is null
+            "#,
+        )
+        .bind(&identity.user)
+        .bind(self.id)
+    }
+}
+
+impl RenameLink {
+    pub fn as_query<'q>(
+        &'q self,
+        id: i64,
+        identity: &'q Identity,
+    ) -> QueryAs<'q, Sqlite, Link, SqliteArguments<'q>> {
+        sqlx::query_as::<_, Link>(
+            r#"
+            update links
+            set source = ?, source_key = ?, modified_by = ?, modified_at = current_timestamp
+            where id = ?
+            returning *
+            "#,
+        )
+        .bind(&self.source)
+        .bind(source::key(&self.source))
+        .bind(&identity.user)
+        .bind(id)
+    }
+}
+
+impl ListDependentAliases {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
+        let mut query = sqlx::query_as::<_, Link>(static_format!(
+            r#"
+            select * from links
+            where links.is_alias and links.personal_to is ? and links.deleted_at is null
+            and {VISIBLE}
+            order by links.source asc
+            "#,
+        ))
+        .bind(&self.personal_to);
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        query
+    }
+}
+
+impl RetargetAlias {
+    pub fn as_query<'q>(
+        &'q self,
+        id: i64,
+        identity: &'q Identity,
+    ) -> QueryAs<'q, Sqlite, Link, SqliteArguments<'q>> {
+        sqlx::query_as::<_, Link>(
+            r#"
+            update links
+            set target = ?, modified_by = ?, modified_at = current_timestamp
+            where id = ? and is_alias
+            returning *
+            "#,
+        )
+        .bind(&self.target)
+        .bind(&identity.user)
+        .bind(id)
+    }
+}
+
+impl UntrashLink {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
+        let mut query = sqlx::query_as::<_, Link>(static_format!(
+            r#"
+            update links
+            set deleted_at = null, deleted_by = null
+            where links.id = ? and links.deleted_at is not null
+            and {VISIBLE}
+            returning *
+            "#,
+        ))
+        .bind(self.id);
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        query
+    }
+}
+
+impl ReleaseSource {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(
+            r#"
+            update links
+            set source_key = source_key || '~' || id
+            where source_key = ? and personal_to is ? and deleted_at is not null
+            "#,
+        )
+        .bind(source::key(&self.source))
+        .bind(&self.personal_to)
+    }
+}
+
+impl ListTrash {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
+        let mut query = sqlx::query_as::<_, Link>(static_format!(
+            r#"
+            select * from links
+            where links.deleted_at is not null
+            and {VISIBLE}
+            order by links.deleted_at desc
+            "#,
+        ));
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        query
+    }
+}
+
+impl ListPersonalLinks {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Link>(
+            r#"
+            select * from links
+            where personal_to = ? and deleted_at is null
+            order by source asc
+            "#,
+        )
+        .bind(&self.user)
+    }
+}
+
+impl ListNamespaces {
+    pub fn as_query(&self) -> QueryScalar<'_, Sqlite, String, SqliteArguments<'_>> {
+        sqlx::query_scalar(
+            r#"
+            select source_key from links
+            where deleted_at is null and personal_to is null and visibility = 'public'
+            and instr(source_key, '/') > 0
+            "#,
+        )
+    }
+}
+
+impl PurgeTrash {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(
+            r#"
+            delete from links
+            where deleted_at is not null and deleted_at < datetime('now', ?)
+            "#,
+        )
+        .bind(format!("-{} days", self.retention_days))
+    }
+}
+
+impl ListEmbeddings {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, (i64, Vec<u8>), SqliteArguments<'_>> {
+        sqlx::query_as(
+            r#"
+            select vec_links.rowid, vec_links.vec_source from vec_links
+            join links on links.id = vec_links.rowid
+            where links.deleted_at is null and links.personal_to is null
+            and links.visibility = 'public'
+            "#,
+        )
+    }
+}
+
+impl ClearTopics {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"delete from link_topics; delete from topics;"#)
+    }
+}
+
+impl CreateTopic {
+    pub fn as_query(&self) -> QueryScalar<'_, Sqlite, i64, SqliteArguments<'_>> {
+        sqlx::query_scalar(r#"insert into topics (label) values (?) returning id"#)
+            .bind(&self.label)
+    }
+}
+
+impl AssignTopic {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"insert into link_topics (link_id, topic_id, distance) values (?, ?, ?)"#)
+            .bind(self.link_id)
+            .bind(self.topic_id)
+            .bind(self.distance)
+    }
+}
+
+const TOPICS_H: &str = r#"
+    select topics.*, count(links.id) as size
+    from topics
+    left join link_topics on link_topics.topic_id = topics.id
+    left join links on links.id = link_topics.link_id and links.deleted_at is null
+        and links.visibility = 'public' and links.personal_to is null
+"#;
+impl ListTopics {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Topic, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Topic>(static_format!(
+            r#"
+            {TOPICS_H}
+            group by topics.id
+            order by size desc, topics.label asc
+            "#
+        ))
+    }
+}
+
+impl GetTopic {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Topic, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Topic>(static_format!(
+            r#"
+            {TOPICS_H}
+            where topics.id = ?
+            group by topics.id
+            "#
+        ))
+        .bind(self.id)
+    }
+}
+
+impl QueryTopicLinks {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Link, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Link>(
+            r#"
+            select links.* from link_topics
+            join links on links.id = link_topics.link_id
+            where link_topics.topic_id = ? and links.deleted_at is null
+            and links.visibility = 'public' and links.personal_to is null
+            order by link_topics.distance asc
+            limit ? offset ?;
+            "#,
+        )
+        .bind(self.id)
+        .bind(self.paging.limit as i64)
+        .bind(self.paging.offset() as i64)
+    }
+
+    pub fn as_count(&self) -> QueryScalar<'_, Sqlite, i64, SqliteArguments<'_>> {
+        sqlx::query_scalar(
+            r#"
+            select count(*) from link_topics
+            join links on links.id = link_topics.link_id
+            where link_topics.topic_id = ? and links.deleted_at is null
+            and links.visibility = 'public' and links.personal_to is null
+            "#,
+        )
+        .bind(self.id)
+    }
+}
+
+impl ListSynonyms {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Synonym, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Synonym>(r#"select * from synonyms order by term asc, synonym asc"#)
+    }
+}
+
+impl MatchSynonyms {
+    /// The query's words, lowercased and padded by single spaces, so terms and synonyms only
+    /// match whole words as in `Synonym::expand`
+    fn words(&self) -> String {
+        let words = self.query.split_whitespace().collect::<Vec<_>>();
+        format!(" {} ", words.join(" ").to_lowercase())
+    }
+
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Synonym, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Synonym>(
+            r#"
+            select * from synonyms
+            where instr(?, ' ' || term || ' ') > 0 or instr(?, ' ' || synonym || ' ') > 0
+            "#,
+        )
+        .bind(self.words())
+        .bind(self.words())
+    }
+}
+
+impl GetSynonym {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Synonym, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Synonym>(r#"select * from synonyms where id = ?"#).bind(self.id)
+    }
+}
+
+impl CreateSynonym {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Synonym, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Synonym>(
+            r#"insert into synonyms (term, synonym) values (lower(trim(?)), lower(trim(?))) returning *"#,
+        )
+        .bind(&self.term)
+        .bind(&self.synonym)
+    }
+}
+
+impl UpdateSynonym {
+    pub fn as_query(&self, id: i64) -> QueryAs<'_, Sqlite, Synonym, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Synonym>(
+            r#"update synonyms set term = lower(trim(?)), synonym = lower(trim(?)) where id = ? returning *"#,
+        )
+        .bind(&self.term)
+        .bind(&self.synonym)
+        .bind(id)
+    }
+}
+
+impl DeleteSynonym {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"delete from synonyms where id = ?"#).bind(self.id)
+    }
+}
+
+const TAGS_H: &str = r#"
+    select tags.*, count(links.id) as size
+    from tags
+    left join link_tags on link_tags.tag_id = tags.id
+    left join links on links.id = link_tags.link_id and links.deleted_at is null
+        and links.visibility = 'public' and links.personal_to is null
+"#;
+impl ListTags {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Tag, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Tag>(static_format!(
+            r#"
+            {TAGS_H}
+            group by tags.id
+            order by tags.name asc
+            "#
+        ))
+    }
+}
+
+impl GetTag {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Tag, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Tag>(static_format!(
+            r#"
+            {TAGS_H}
+            where tags.id = ?
+            group by tags.id
+            "#
+        ))
+        .bind(self.id)
+    }
+}
+
+impl CreateTag {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Tag, SqliteArguments<'_>> {
+        sqlx::query_as::<_, Tag>(
+            r#"insert into tags (name) values (lower(trim(?))) returning *, 0 as size"#,
+        )
+        .bind(&self.name)
+    }
+}
+
+impl UpdateTag {
+    pub fn as_query(&self, id: i64) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"update tags set name = lower(trim(?)) where id = ?"#)
+            .bind(&self.name)
+            .bind(id)
+    }
+}
+
+impl DeleteTag {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"delete from tags where id = ?"#).bind(self.id)
+    }
+}
+
+impl ListLinkTags {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, (i64, String), SqliteArguments<'_>> {
+        sqlx::query_as(
+            r#"
+            select link_tags.link_id, tags.name from link_tags
+            join tags on tags.id = link_tags.tag_id
+            where link_tags.link_id in (select value from json_each(?))
+            order by tags.name asc
+            "#,
+        )
+        .bind(serde_json::to_string(&self.link_ids).unwrap_or_default())
+    }
+}
+
+impl TagLink {
+    pub fn as_tag_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"insert or ignore into tags (name) values (lower(trim(?)))"#).bind(&self.name)
+    }
+
     pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
-        sqlx::query(r#"delete from links where id = ?"#).bind(self.id)
+        sqlx::query(
+            r#"
+            insert or ignore into link_tags (link_id, tag_id)
+            select ?, id from tags where name = lower(trim(?))
+            "#,
+        )
+        .bind(self.link_id)
+        .bind(&self.name)
+    }
+}
+
+impl UntagLink {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(
+            r#"
+            delete from link_tags
+            where link_id = ?
+            and tag_id in (select id from tags where name = lower(trim(?)))
+            "#,
+        )
+        .bind(self.link_id)
+        .bind(&self.name)
+    }
+}
+
+impl ClearLinkTags {
+    pub fn as_query(&self) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+        sqlx::query(r#"delete from link_tags where link_id = ?"#).bind(self.link_id)
+    }
+}
+
+impl CreateRevision {
+    pub fn as_query<'q>(
+        &'q self,
+        identity: &'q Identity,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        sqlx::query(
+            r#"
+            insert into link_revisions (revised_by, link_id, action, before, after)
+            values (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&identity.user)
+        .bind(self.link_id)
+        .bind(self.action.to_string())
+        .bind(self.before.as_ref().map(Json))
+        .bind(self.after.as_ref().map(Json))
+    }
+}
+
+impl ListRevisions {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Revision, SqliteArguments<'_>> {
+        let mut query = sqlx::query_as::<_, Revision>(static_format!(
+            r#"
+            select link_revisions.* from link_revisions
+            join links on links.id = link_revisions.link_id
+            where link_revisions.link_id = ?
+            and {VISIBLE}
+            order by link_revisions.id desc
+            "#,
+        ))
+        .bind(self.link_id);
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        query
+    }
+}
+
+impl GetRevision {
+    pub fn as_query(&self) -> QueryAs<'_, Sqlite, Revision, SqliteArguments<'_>> {
+        let mut query = sqlx::query_as::<_, Revision>(static_format!(
+            r#"
+            select link_revisions.* from link_revisions
+            join links on links.id = link_revisions.link_id
+            where link_revisions.id = ?
+            and {VISIBLE}
+            "#,
+        ))
+        .bind(self.id);
+        for bind in visible_binds(&self.identity) {
+            query = query.bind(bind);
+        }
+        query
+    }
+}
+
+impl RestoreLink {
+    pub fn as_query<'q>(
+        &'q self,
+        id: i64,
+        identity: &'q Identity,
+    ) -> QueryAs<'q, Sqlite, Link, SqliteArguments<'q>> {
+        sqlx::query_as::<_, Link>(
+            r#"
+            insert into links (
+                id, source, source_key, is_alias, target, description, owner, valid_from,
+                valid_until, deprecated_at, replaced_by, deprecation_message, personal_to,
+                visibility, is_collection, redirect_status, forwarding, created_by, modified_by
+            )
+            values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            on conflict (id) do update
+            set source = excluded.source, source_key = excluded.source_key,
+                is_alias = excluded.is_alias, target = excluded.target,
+                description = excluded.description, owner = excluded.owner,
+                valid_from = excluded.valid_from, valid_until = excluded.valid_until,
+                deprecated_at = excluded.deprecated_at, replaced_by = excluded.replaced_by,
+                deprecation_message = excluded.deprecation_message,
+                visibility = excluded.visibility, is_collection = excluded.is_collection,
+                redirect_status = excluded.redirect_status, forwarding = excluded.forwarding,
+                modified_by = excluded.modified_by, modified_at = current_timestamp,
+                deleted_at = null, deleted_by = null
+            returning *
+            "#,
+        )
+        .bind(id)
+        .bind(&self.source)
+        .bind(source::key(&self.source))
+        .bind(self.is_alias)
+        .bind(&self.target)
+        .bind(&self.description)
+        .bind(&self.owner)
+        .bind(self.valid_from)
+        .bind(self.valid_until)
+        .bind(self.deprecated_at)
+        .bind(&self.replaced_by)
+        .bind(&self.deprecation_message)
+        .bind(&self.personal_to)
+        .bind(self.visibility)
+        .bind(self.is_collection)
+        .bind(self.redirect_status)
+        .bind(self.forwarding)
+        .bind(&identity.user)
+        .bind(&identity.user)
     }
 }
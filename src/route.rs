@@ -7,17 +7,38 @@ use crate::{
     AppState,
     handler::{
         api,
-        htmx::{self, index_handler},
+        htmx::{
+            self, index_handler, namespace_page_handler, namespaces_page_handler,
+            synonyms_page_handler, topics_page_handler, trash_page_handler,
+        },
         search,
     },
 };
 
+/// Top-level paths served by the router, which a source must not shadow
+pub const ROUTE_PREFIXES: &[&str] = &[
+    "api",
+    "go",
+    "search",
+    "static",
+    "topics",
+    "synonyms",
+    "trash",
+    "ns",
+    "favicon.ico",
+];
+
 pub fn create_router(app_state: Arc<AppState>) -> Router {
     Router::new()
         .nest_service("/search", search::router(app_state.clone()))
         .nest_service("/api", api::router(app_state.clone()))
         .nest_service("/go", htmx::router(app_state.clone()))
         .route("/", get(index_handler))
+        .route("/topics", get(topics_page_handler))
+        .route("/synonyms", get(synonyms_page_handler))
+        .route("/trash", get(trash_page_handler))
+        .route("/ns", get(namespaces_page_handler))
+        .route("/ns/{*namespace}", get(namespace_page_handler))
         .fallback_service(ServeDir::new("static"))
         .with_state(app_state)
 }
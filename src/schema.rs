@@ -1,13 +1,24 @@
 use std::fmt::Display;
 
-use serde::{Deserialize, Serialize};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Deserializer, Serialize, de::Error};
+use serde_json::Value;
+
+use crate::{
+    filter::Filter,
+    identity::Identity,
+    model::{Forwarding, REDIRECT_STATUSES, Visibility, default_redirect_status},
+};
 
 #[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum SortMethod {
     #[default]
+    Relevance,
     Alphabetical,
     Created,
     Updated,
+    Hits,
+    LastUsed,
 }
 impl Display for SortMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -32,7 +43,6 @@ pub struct SortOptions {
     #[serde(default)]
     pub order: SortOrder,
 }
-#[allow(dead_code)]
 impl SortOptions {
     pub fn as_query(&self) -> String {
         let Self { sort_by, order } = self;
@@ -80,28 +90,50 @@ pub enum SearchMethod {
     Semantic,
     DamerauLevenshtein,
 }
+impl SearchMethod {
+    pub const ALL: [Self; 2] = [Self::Semantic, Self::DamerauLevenshtein];
+}
 impl Display for SearchMethod {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{self:?}")
     }
 }
+const BOOST_WEIGHT: f64 = 1.0;
+
+/// Show a page about the link instead of redirecting to it
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PreviewOptions {
+    #[serde(default, deserialize_with = "flag")]
+    pub preview: bool,
+}
+
 #[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
 pub struct SearchOptions {
     #[serde(default)]
     pub query: String,
     #[serde(default)]
     pub method: SearchMethod,
+    #[serde(default)]
+    pub boost: bool,
 }
 impl SearchOptions {
     pub fn as_query(&self) -> String {
-        let Self { query, method } = self;
+        let Self {
+            query,
+            method,
+            boost,
+        } = self;
         let method = method.to_string();
         if self != &Self::default() {
-            format!("&query={query}&method={method}")
+            format!("&query={query}&method={method}&boost={boost}")
         } else {
             "".to_string()
         }
     }
+    /// Weight given to usage relative to relevance, zero unless boosting
+    pub fn boost_weight(&self) -> f64 {
+        if self.boost { BOOST_WEIGHT } else { 0.0 }
+    }
 }
 
 fn default_editable() -> bool {
@@ -113,40 +145,640 @@ pub struct ViewOptions {
     pub editable: bool,
 }
 
+pub struct CreateClick {
+    pub link_id: i64,
+    pub query: String,
+    /// The variant served, if any
+    pub variant_id: Option<i64>,
+    /// Sources resolved through, ending at the link's own
+    pub alias_chain: Vec<String>,
+    /// Salted hash of the user, if configured
+    pub user_hash: Option<String>,
+    /// When the redirect was served, as clicks are written in batches
+    pub clicked_at: NaiveDateTime,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GetLink {
     pub id: i64,
+    /// Who is asking, as links they may not see don't exist for them
+    #[serde(skip)]
+    pub identity: Identity,
 }
 
 pub struct DeleteLink {
     pub id: i64,
 }
 
+pub struct UntrashLink {
+    pub id: i64,
+    pub identity: Identity,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RenameLink {
+    pub source: String,
+    /// Also point aliases targeting the old source at the new one
+    #[serde(default)]
+    pub rewrite_aliases: bool,
+}
+
+/// Aliases alongside a link, personal if it is, to be matched on their target's source key
+pub struct ListDependentAliases {
+    pub personal_to: Option<String>,
+    pub identity: Identity,
+}
+
+pub struct RetargetAlias {
+    pub target: String,
+}
+
+/// Free up a source for a new link by moving any trashed link holding it aside to `key~id`, so
+/// that it can still be restored
+pub struct ReleaseSource {
+    pub source: String,
+    /// Only trashed links with the same owner, or none for global links, are released
+    pub personal_to: Option<String>,
+}
+
+/// Trashed links the user may see
+pub struct ListTrash {
+    pub identity: Identity,
+}
+
+pub struct ListPersonalLinks {
+    pub user: String,
+}
+
+pub struct ListNamespaces {}
+
+/// Permanently remove links that have been in the trash longer than the retention period
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PurgeTrash {
+    pub retention_days: u32,
+}
+impl Default for PurgeTrash {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FindLink {
     pub source: String,
+    /// Whose personal link to prefer over the global one, and who restricted links must be
+    /// visible to
+    #[serde(skip)]
+    pub identity: Identity,
+}
+
+pub struct ListSourceKeys {}
+
+/// The revision of `source::key` stored source keys were computed with
+pub struct GetSourceKeyVersion {}
+
+pub struct SetSourceKeyVersion {
+    pub version: i64,
+}
+
+pub struct SetSourceKey {
+    pub id: i64,
+    pub source_key: String,
 }
 
+/// Links whose target is canonically the same URL
 #[derive(Debug, Deserialize, Serialize)]
+pub struct FindLinksByTarget {
+    #[serde(alias = "target")]
+    pub url: String,
+    #[serde(skip)]
+    pub identity: Identity,
+}
+
+/// The public global aliases as target and source, to be matched on their target's source key
+pub struct ListLinkAliases {}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct QueryLinks {
     #[serde(flatten)]
     pub paging: PagingOptions,
     #[serde(flatten)]
     pub search: SearchOptions,
+    #[serde(flatten)]
+    pub sort: SortOptions,
+    /// Field filters and ranking text parsed from `search.query`, along with any clauses the caller
+    /// set beforehand
+    #[serde(skip)]
+    pub filter: Filter,
+    /// Synonymous rewrites of the ranking text, including the text itself
+    #[serde(skip)]
+    pub expansions: Vec<String>,
+    #[serde(skip)]
+    pub identity: Identity,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Accepts RFC 3339-ish timestamps without a zone, as sent by `datetime-local` inputs, or a bare
+/// date for midnight, all in UTC
+fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+}
+
+fn datetime<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_datetime(value.trim())
+        .ok_or_else(|| D::Error::custom(format!("invalid date and time '{value}'")))
+}
+
+/// Group names as a list, or comma-separated as sent by forms
+fn group_names<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Names {
+        List(Vec<String>),
+        Text(String),
+    }
+    let names = match Names::deserialize(deserializer)? {
+        Names::List(names) => names,
+        Names::Text(names) => names.split(',').map(str::to_string).collect(),
+    };
+    Ok(names
+        .iter()
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Ids as a list, or comma-separated as sent by forms
+fn id_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Ids {
+        List(Vec<i64>),
+        Text(String),
+    }
+    match Ids::deserialize(deserializer)? {
+        Ids::List(ids) => Ok(ids),
+        Ids::Text(ids) => ids
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                id.parse()
+                    .map_err(|_| D::Error::custom(format!("invalid id '{id}'")))
+            })
+            .collect(),
+    }
+}
+
+/// An optional redirect status, as a number or as sent by forms, with an empty value for none
+fn redirect_status<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u16>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Status {
+        Number(u16),
+        Text(String),
+    }
+    let status = match Option::<Status>::deserialize(deserializer)? {
+        Some(Status::Number(status)) => status,
+        Some(Status::Text(status)) if status.trim().is_empty() => return Ok(None),
+        Some(Status::Text(status)) => status
+            .trim()
+            .parse()
+            .map_err(|_| D::Error::custom(format!("invalid redirect status '{status}'")))?,
+        None => return Ok(None),
+    };
+    if !REDIRECT_STATUSES.contains(&status) {
+        return Err(D::Error::custom(format!(
+            "redirect status {status} is not one of {REDIRECT_STATUSES:?}"
+        )));
+    }
+    Ok(Some(status))
+}
+
+/// A flag given as `1`, `true`, `yes` or `on`, as in `?preview=1`
+fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Ok(matches!(
+        value.trim().to_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    ))
+}
+
+/// An optional link source, with an empty value for none
+fn optional_source<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?
+        .map(|source| source.trim().to_string())
+        .filter(|source| !source.is_empty()))
+}
+
+/// As `datetime`, with an empty value for none
+fn optional_datetime<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NaiveDateTime>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if !value.trim().is_empty() => parse_datetime(value.trim())
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("invalid date and time '{value}'"))),
+        _ => Ok(None),
+    }
+}
+
+/// As `optional_datetime`, distinguishing a present but empty value from an absent one
+fn present_datetime<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Option<NaiveDateTime>>, D::Error> {
+    optional_datetime(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CreateLink {
     pub source: String,
     #[serde(default)]
     pub is_alias: bool,
+    #[serde(default)]
     pub target: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, deserialize_with = "optional_datetime")]
+    pub valid_from: Option<NaiveDateTime>,
+    #[serde(default, deserialize_with = "optional_datetime")]
+    pub valid_until: Option<NaiveDateTime>,
+    /// Visible only to, and shadowing global links for, the creating user
+    #[serde(default)]
+    pub personal: bool,
+    /// A curated list of other links, whose target is ignored
+    #[serde(default)]
+    pub is_collection: bool,
+    /// Defaults to 303 See Other
+    #[serde(default, deserialize_with = "redirect_status")]
+    pub redirect_status: Option<u16>,
+    #[serde(default)]
+    pub forwarding: Forwarding,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UpdateLink {
     pub source: String,
     #[serde(default)]
     pub is_alias: bool,
     pub target: String,
+    /// Left unchanged if omitted
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Left unchanged if omitted, cleared if empty
+    #[serde(default, deserialize_with = "present_datetime")]
+    pub valid_from: Option<Option<NaiveDateTime>>,
+    /// Left unchanged if omitted, cleared if empty
+    #[serde(default, deserialize_with = "present_datetime")]
+    pub valid_until: Option<Option<NaiveDateTime>>,
+    /// Left unchanged if omitted
+    #[serde(default, deserialize_with = "redirect_status")]
+    pub redirect_status: Option<u16>,
+    /// Left unchanged if omitted
+    #[serde(default)]
+    pub forwarding: Option<Forwarding>,
+}
+
+pub struct ListEmbeddings {}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct ClusterTopics {
+    #[serde(default)]
+    pub k: Option<usize>,
+}
+
+pub struct ClearTopics {}
+
+pub struct CreateTopic {
+    pub label: String,
+}
+
+pub struct AssignTopic {
+    pub link_id: i64,
+    pub topic_id: i64,
+    pub distance: f32,
+}
+
+pub struct ListTopics {}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GetTopic {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QueryTopicLinks {
+    pub id: i64,
+    #[serde(flatten)]
+    pub paging: PagingOptions,
+}
+
+pub struct ListSynonyms {}
+
+pub struct MatchSynonyms {
+    pub query: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GetSynonym {
+    pub id: i64,
+}
+
+pub struct DeleteSynonym {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateSynonym {
+    pub term: String,
+    pub synonym: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateSynonym {
+    pub term: String,
+    pub synonym: String,
+}
+
+pub struct ListTags {}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GetTag {
+    pub id: i64,
+}
+
+pub struct DeleteTag {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateTag {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateTag {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SetTags {
+    pub tags: Vec<String>,
+}
+
+pub struct ListLinkTags {
+    pub link_ids: Vec<i64>,
+}
+
+pub struct TagLink {
+    pub link_id: i64,
+    pub name: String,
+}
+
+pub struct UntagLink {
+    pub link_id: i64,
+    pub name: String,
+}
+
+pub struct ClearLinkTags {
+    pub link_id: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TransferLink {
+    pub owner: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeprecateLink {
+    /// Source of the link to use instead
+    #[serde(default, deserialize_with = "optional_source")]
+    pub replaced_by: Option<String>,
+    #[serde(default)]
+    pub message: String,
+}
+
+pub struct UndeprecateLink {}
+
+/// Record a change to a link's attachments as an edit of the link
+pub struct TouchLink {
+    pub id: i64,
+}
+
+pub struct ListUsage {
+    pub link_ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CoOwner {
+    pub owner: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SetVisibility {
+    pub visibility: Visibility,
+    /// Groups whose members may see a restricted link
+    #[serde(default, deserialize_with = "group_names")]
+    pub groups: Vec<String>,
+}
+
+pub struct ListLinkGroups {
+    pub link_ids: Vec<i64>,
+}
+
+pub struct ClearLinkGroups {
+    pub link_id: i64,
+}
+
+pub struct AddLinkGroup {
+    pub link_id: i64,
+    pub name: String,
+}
+
+pub struct ListCoOwners {
+    pub link_ids: Vec<i64>,
+}
+
+pub struct AddCoOwner {
+    pub link_id: i64,
+    pub owner: String,
+}
+
+pub struct RemoveCoOwner {
+    pub link_id: i64,
+    pub owner: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ScheduleTarget {
+    pub target: String,
+    #[serde(deserialize_with = "datetime")]
+    pub active_from: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SplitTarget {
+    pub target: String,
+    /// Percentage of traffic to send to the target
+    pub weight: i64,
+}
+
+/// A link to add to a collection
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CollectLink {
+    pub source: String,
+    #[serde(default)]
+    pub note: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AnnotateItem {
+    #[serde(default)]
+    pub note: String,
+}
+
+/// Collection items in their new order
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OrderItems {
+    #[serde(deserialize_with = "id_list")]
+    pub item_ids: Vec<i64>,
+}
+
+pub struct ListCollectionItems {
+    pub link_ids: Vec<i64>,
+}
+
+pub struct AddCollectionItem {
+    pub collection_id: i64,
+    pub link_id: i64,
+    pub note: String,
+}
+
+pub struct UpdateCollectionItem {
+    pub collection_id: i64,
+    pub id: i64,
+    pub note: String,
+}
+
+pub struct RemoveCollectionItem {
+    pub collection_id: i64,
+    pub id: i64,
+}
+
+pub struct ReorderCollectionItems {
+    pub collection_id: i64,
+    pub item_ids: Vec<i64>,
+}
+
+pub struct ListSchedules {
+    pub link_ids: Vec<i64>,
+}
+
+pub struct AddSchedule {
+    pub link_id: i64,
+    pub target: String,
+    pub active_from: NaiveDateTime,
+}
+
+pub struct RemoveSchedule {
+    pub link_id: i64,
+    pub id: i64,
+}
+
+pub struct GetStats {
+    pub link_id: i64,
+    pub identity: Identity,
+}
+
+pub struct ListVariants {
+    pub link_ids: Vec<i64>,
+}
+
+pub struct AddVariant {
+    pub link_id: i64,
+    pub target: String,
+    pub weight: i64,
+}
+
+pub struct RemoveVariant {
+    pub link_id: i64,
+    pub id: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+}
+impl Display for RevisionAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+pub struct CreateRevision {
+    pub link_id: i64,
+    pub action: RevisionAction,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+pub struct ListRevisions {
+    pub link_id: i64,
+    pub identity: Identity,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GetRevision {
+    pub id: i64,
+    #[serde(skip)]
+    pub identity: Identity,
+}
+
+/// The restorable fields of a link snapshot: the link's own row, leaving tags, groups, co-owners,
+/// variants and schedules as they are, since older snapshots don't reliably record them
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RestoreLink {
+    pub source: String,
+    pub is_alias: bool,
+    pub target: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub valid_from: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub valid_until: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub deprecated_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub replaced_by: Option<String>,
+    #[serde(default)]
+    pub deprecation_message: Option<String>,
+    #[serde(default)]
+    pub personal_to: Option<String>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub is_collection: bool,
+    #[serde(default = "default_redirect_status")]
+    pub redirect_status: u16,
+    #[serde(default)]
+    pub forwarding: Forwarding,
 }
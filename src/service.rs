@@ -1,45 +1,129 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, PoisonError},
+    time::Duration,
+};
 
-use sqlx::Error;
+use sqlx::{Error, types::Json};
 
 use crate::{
     AppState,
-    model::Link,
-    schema::{CreateLink, DeleteLink, FindLink, GetLink, QueryLinks, UpdateLink},
+    cluster::{self, Embedding},
+    filter::Filter,
+    identity::{Forbidden, Identity},
+    model::{Forwarding, Link, Namespace, Revision, Stats, Synonym, Tag, Topic, Visibility},
+    schema::{
+        AddCoOwner, AddCollectionItem, AddLinkGroup, AddSchedule, AddVariant, AssignTopic,
+        ClearLinkGroups, ClearLinkTags, ClearTopics, ClusterTopics, CollectLink, CreateClick,
+        CreateLink, CreateRevision, CreateSynonym, CreateTag, CreateTopic, DeleteLink,
+        DeleteSynonym, DeleteTag, DeprecateLink, FindLink, FindLinksByTarget, GetLink, GetRevision,
+        GetSourceKeyVersion, GetStats, GetSynonym, GetTag, GetTopic, ListCoOwners,
+        ListCollectionItems, ListDependentAliases, ListEmbeddings, ListLinkAliases, ListLinkGroups,
+        ListLinkTags, ListNamespaces, ListPersonalLinks, ListRevisions, ListSchedules,
+        ListSourceKeys, ListSynonyms, ListTags, ListTopics, ListTrash, ListUsage, ListVariants,
+        MatchSynonyms, PurgeTrash, QueryLinks, QueryTopicLinks, ReleaseSource, RemoveCoOwner,
+        RemoveCollectionItem, RemoveSchedule, RemoveVariant, RenameLink, ReorderCollectionItems,
+        RestoreLink, RetargetAlias, RevisionAction, SetSourceKey, SetSourceKeyVersion, SetTags,
+        SetVisibility, TagLink, TouchLink, TransferLink, UndeprecateLink, UntagLink, UntrashLink,
+        UpdateCollectionItem, UpdateLink, UpdateSynonym, UpdateTag,
+    },
+    source, tag,
+    target::{self, TargetError},
 };
 
+/// Queued clicks that trigger a batch before the next tick
+const MAX_PENDING_CLICKS: usize = 1000;
+
+/// Find a link in a namespace first, falling back to the global link of the same name, preferring
+/// the user's personal links in either
+async fn find_scoped_link(
+    app_state: &AppState,
+    identity: &Identity,
+    namespace: Option<&str>,
+    source: &str,
+) -> Result<Option<Link>, Error> {
+    if let Some(namespace) = namespace {
+        let scoped = FindLink {
+            source: format!("{namespace}/{source}"),
+            identity: identity.clone(),
+        };
+        if let Some(link) = find_link(app_state, &scoped).await? {
+            return Ok(Some(link));
+        }
+    }
+    find_link(
+        app_state,
+        &FindLink {
+            source: source.to_string(),
+            identity: identity.clone(),
+        },
+    )
+    .await
+}
+
+/// Resolve a source in the caller's namespace, then globally, following aliases from the
+/// namespace of each alias
 pub async fn resolve_alias(
     app_state: Arc<AppState>,
+    identity: &Identity,
     initial: String,
 ) -> Result<Option<Link>, Error> {
-    let initial = find_link(&app_state.clone(), &FindLink { source: initial }).await?;
+    let mut links = resolve_alias_chain(app_state, identity, initial).await?;
 
-    if initial.is_none() {
-        return Ok(None);
+    // An alias still leading on has a missing target
+    let mut link = links.pop().filter(|link| !link.leads_on());
+    if let Some(link) = &mut link {
+        link.via = links.into_iter().map(|alias| alias.source).collect();
     }
-    let mut links = vec![initial.unwrap()];
+    Ok(link)
+}
+
+/// Every link passed through resolving a source, ending at its destination or at the alias whose
+/// target is missing
+pub async fn resolve_alias_chain(
+    app_state: Arc<AppState>,
+    identity: &Identity,
+    initial: String,
+) -> Result<Vec<Link>, Error> {
+    let mut found = find_scoped_link(
+        &app_state,
+        identity,
+        identity.namespace.as_deref(),
+        &initial,
+    )
+    .await?;
+
+    if found.is_none() {
+        found = find_synonymous_link(&app_state, identity, &initial).await?;
+    }
+    if found.is_none() {
+        return Ok(vec![]);
+    }
+    let mut links = vec![found.unwrap()];
 
     loop {
-        let head = links.last().unwrap();
+        let head = links.last_mut().unwrap();
+        head.target = head.active_target().to_string();
+
+        if !head.leads_on() {
+            return Ok(links);
+        }
         println!("🔃 Alias definition '{}' -> '{}'", head.source, head.target);
 
-        let resolved = find_link(
-            &app_state.clone(),
-            &FindLink {
-                source: head.target.clone(),
-            },
+        let resolved = find_scoped_link(
+            &app_state,
+            identity,
+            source::namespace(&head.source),
+            &head.target,
         )
         .await?;
 
         if resolved.is_none() {
-            return Ok(None);
+            return Ok(links);
         }
         let link = resolved.unwrap();
 
-        if !link.is_alias {
-            return Ok(Some(link));
-        }
-        if links.contains(&link) {
+        if links.iter().any(|seen| seen.id == link.id) {
             println!("♾️ Recursive alias definition '{}'", link.source);
             return Err(Error::RowNotFound);
         }
@@ -47,6 +131,32 @@ pub async fn resolve_alias(
     }
 }
 
+async fn find_synonymous_link(
+    app_state: &AppState,
+    identity: &Identity,
+    source: &str,
+) -> Result<Option<Link>, Error> {
+    let synonyms = MatchSynonyms {
+        query: source.to_string(),
+    }
+    .as_query()
+    .fetch_all(&app_state.db)
+    .await?;
+
+    for expansion in Synonym::expand(source, &synonyms).into_iter().skip(1) {
+        let find = FindLink {
+            source: expansion,
+            identity: identity.clone(),
+        };
+        let link = find_link(app_state, &find).await?;
+        if link.is_some() {
+            return Ok(link);
+        }
+    }
+
+    Ok(None)
+}
+
 pub async fn query_links(
     app_state: &AppState,
     query: &QueryLinks,
@@ -55,48 +165,1334 @@ pub async fn query_links(
         "💽 Search for '{}' with strategy '{}', page '{}' size '{}'",
         query.search.query, query.search.method, query.paging.page, query.paging.limit,
     );
-    let links = query.as_query().fetch_all(&app_state.db).await?;
-    let count = query.as_count().fetch_one(&app_state.db).await?;
+    // Conditions the caller brings along, such as a tag page's tag, narrow the typed query
+    let mut filter = Filter::parse(&query.search.query);
+    filter.clauses.extend(query.filter.clauses.iter().cloned());
+    let synonyms = MatchSynonyms {
+        query: filter.text.clone(),
+    }
+    .as_query()
+    .fetch_all(&app_state.db)
+    .await?;
+    let query = QueryLinks {
+        paging: query.paging,
+        search: query.search.clone(),
+        expansions: Synonym::expand(&filter.text, &synonyms),
+        filter,
+        identity: query.identity.clone(),
+        sort: query.sort,
+    };
+
+    let sql = query.as_sql();
+    let mut links = query.as_query(&sql).fetch_all(&app_state.db).await?;
+    attach_details(app_state, &mut links).await?;
+    let sql = query.as_count_sql();
+    let count = query.as_count(&sql).fetch_one(&app_state.db).await?;
     let last = (count as u64).div_ceil(query.paging.limit);
 
     Ok((links, last))
 }
 
-pub async fn create_link(app_state: &AppState, create: &CreateLink) -> Result<Link, Error> {
+pub async fn get_stats(app_state: &AppState, get: &GetStats) -> Result<Stats, Error> {
+    println!("💽 Stats for '{}'", get.link_id);
+    let stats = get.as_query().fetch_one(&app_state.db).await?;
+
+    Ok(stats)
+}
+
+/// Queue a click to be written with the next batch, keeping redirects off the database
+pub fn record_click(app_state: &Arc<AppState>, click: CreateClick) {
+    println!("💽 Click '{}' for '{}'", click.link_id, click.query);
+    let pending = {
+        let mut clicks = app_state
+            .pending_clicks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        clicks.push(click);
+        clicks.len()
+    };
+    // Flush early rather than let a burst of clicks pile up until the next batch
+    if pending == MAX_PENDING_CLICKS {
+        let app_state = app_state.clone();
+        tokio::spawn(async move {
+            if let Err(err) = flush_clicks(&app_state).await {
+                eprintln!("📈 Failed to record clicks: {err}");
+            }
+        });
+    }
+}
+
+/// Write all queued clicks in one transaction, returning how many were written
+pub async fn flush_clicks(app_state: &AppState) -> Result<usize, Error> {
+    let clicks = std::mem::take(
+        &mut *app_state
+            .pending_clicks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner),
+    );
+    if clicks.is_empty() {
+        return Ok(0);
+    }
+    println!("💽 Record {} clicks", clicks.len());
+
+    let mut tx = app_state.db.begin().await?;
+    for click in &clicks {
+        click.as_query().execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+
+    Ok(clicks.len())
+}
+
+/// Check a new or changed source against the configured naming policy
+fn check_source(app_state: &AppState, identity: &Identity, source: &str) -> Result<(), Error> {
+    app_state
+        .source_policy
+        .check(source, identity)
+        .map_err(|err| Error::Encode(Box::new(err)))
+}
+
+/// Check a tag name before it is created or attached
+fn check_tag(name: &str) -> Result<(), Error> {
+    tag::check(name).map_err(|err| Error::Encode(Box::new(err)))
+}
+
+/// Refuse a change to a link unless the user owns or co-owns it, or is a source admin; links
+/// nobody owns stay open to everyone, and personal links only ever to their user
+fn check_editable(app_state: &AppState, identity: &Identity, link: &Link) -> Result<(), Error> {
+    let editable = match (&link.personal_to, &link.owner) {
+        (Some(personal_to), _) => identity.user.as_ref() == Some(personal_to),
+        (None, None) => true,
+        (None, Some(owner)) => {
+            identity
+                .user
+                .as_ref()
+                .is_some_and(|user| user == owner || link.co_owners.contains(user))
+                || app_state.source_policy.is_admin(identity)
+        }
+    };
+    if editable {
+        return Ok(());
+    }
+    Err(forbidden(identity, link))
+}
+
+fn forbidden(identity: &Identity, link: &Link) -> Error {
+    Error::Encode(Box::new(Forbidden {
+        link_id: link.id,
+        user: identity.user.clone(),
+    }))
+}
+
+/// Check and tidy a link's target against the configured policy; aliases target other sources
+fn normalize_target(app_state: &AppState, is_alias: bool, target: &str) -> Result<String, Error> {
+    if is_alias {
+        return Ok(target.trim().to_string());
+    }
+    app_state
+        .target_policy
+        .normalize(target)
+        .map_err(|err| Error::Encode(Box::new(err)))
+}
+
+pub async fn create_link(
+    app_state: &AppState,
+    identity: &Identity,
+    create: &CreateLink,
+) -> Result<Link, Error> {
     println!("💽 Create new '{}'", create.source);
-    let link = create.as_query().fetch_one(&app_state.db).await?;
+    check_source(app_state, identity, &create.source)?;
+    if create.personal && identity.user.is_none() {
+        return Err(Error::Encode(
+            "personal links need an authenticated user".into(),
+        ));
+    }
+    if create.is_alias && create.is_collection {
+        return Err(Error::Encode(
+            "a link cannot be both an alias and a collection".into(),
+        ));
+    }
+    let target = if create.is_collection {
+        "".to_string()
+    } else {
+        normalize_target(app_state, create.is_alias, &create.target)?
+    };
+    let create = &CreateLink {
+        target,
+        ..create.clone()
+    };
+    let mut tx = app_state.db.begin().await?;
+    ReleaseSource {
+        source: create.source.clone(),
+        personal_to: identity.user.clone().filter(|_| create.personal),
+    }
+    .as_query()
+    .execute(&mut *tx)
+    .await?;
+    let link = create.as_query(identity).fetch_one(&mut *tx).await?;
+    snapshot(RevisionAction::Create, None, Some(&link))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
 
     Ok(link)
 }
 
 pub async fn find_link(app_state: &AppState, find: &FindLink) -> Result<Option<Link>, Error> {
     println!("💽 Find '{}'", find.source);
-    let link = find.as_query().fetch_optional(&app_state.db).await?;
+    let mut link = find.as_query().fetch_optional(&app_state.db).await?;
+    if let Some(link) = link.as_mut() {
+        attach_details(app_state, std::slice::from_mut(link)).await?;
+    }
 
     Ok(link)
 }
 
 pub async fn get_link(app_state: &AppState, get: &GetLink) -> Result<Link, Error> {
     println!("💽 Get '{}'", get.id);
-    let link = get.as_query().fetch_one(&app_state.db).await?;
+    let mut link = get.as_query().fetch_one(&app_state.db).await?;
+    attach_details(app_state, std::slice::from_mut(&mut link)).await?;
 
     Ok(link)
 }
 
 pub async fn edit_link(
     app_state: &AppState,
+    identity: &Identity,
     get: &GetLink,
     update: &UpdateLink,
 ) -> Result<Link, Error> {
     println!("💽 Edit '{}'", get.id);
-    get_link(app_state, get).await?;
-    let link = update.as_query(get.id).fetch_one(&app_state.db).await?;
+    let before = get_link(app_state, get).await?;
+    check_editable(app_state, identity, &before)?;
+    let target = if before.is_collection {
+        "".to_string()
+    } else {
+        normalize_target(app_state, update.is_alias, &update.target)?
+    };
+    let update = &UpdateLink {
+        target,
+        ..update.clone()
+    };
+    let rekeyed = source::key(&before.source) != source::key(&update.source);
+    if rekeyed {
+        check_source(app_state, identity, &update.source)?;
+    }
+    let mut tx = app_state.db.begin().await?;
+    if rekeyed {
+        ReleaseSource {
+            source: update.source.clone(),
+            personal_to: before.personal_to.clone(),
+        }
+        .as_query()
+        .execute(&mut *tx)
+        .await?;
+    }
+    let mut link = update
+        .as_query(get.id, identity)
+        .fetch_one(&mut *tx)
+        .await?;
+    attach_details(app_state, std::slice::from_mut(&mut link)).await?;
+    snapshot(RevisionAction::Update, Some(&before), Some(&link))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
 
     Ok(link)
 }
 
-pub async fn delete_link(app_state: &AppState, delete: &DeleteLink) -> Result<(), Error> {
+pub async fn transfer_link(
+    app_state: &AppState,
+    identity: &Identity,
+    get: &GetLink,
+    transfer: &TransferLink,
+) -> Result<Link, Error> {
+    println!("💽 Transfer '{}' to '{}'", get.id, transfer.owner);
+    let before = get_link(app_state, get).await?;
+    check_editable(app_state, identity, &before)?;
+    let mut tx = app_state.db.begin().await?;
+    transfer
+        .as_query(get.id, identity)
+        .execute(&mut *tx)
+        .await?;
+    let mut after = get.as_unscoped_query().fetch_one(&mut *tx).await?;
+    attach_details(app_state, std::slice::from_mut(&mut after)).await?;
+    snapshot(RevisionAction::Update, Some(&before), Some(&after))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(after)
+}
+
+pub async fn find_links_by_target(
+    app_state: &AppState,
+    find: &FindLinksByTarget,
+) -> Result<Vec<Link>, Error> {
+    println!("💽 Find by target '{}'", find.url);
+    let canonical = target::canonical(&find.url);
+    let mut links = find
+        .as_query()
+        .fetch_all(&app_state.db)
+        .await?
+        .into_iter()
+        .filter(|link| target::canonical(&link.target) == canonical)
+        .collect::<Vec<_>>();
+    attach_details(app_state, &mut links).await?;
+
+    Ok(links)
+}
+
+/// Aliases whose target is the given link's source, among the global aliases for a global link
+/// or its user's personal aliases for a personal one
+async fn dependent_aliases(
+    app_state: &AppState,
+    identity: &Identity,
+    link: &Link,
+) -> Result<Vec<Link>, Error> {
+    let key = source::key(&link.source);
+    let aliases = ListDependentAliases {
+        personal_to: link.personal_to.clone(),
+        identity: identity.clone(),
+    }
+    .as_query()
+    .fetch_all(&app_state.db)
+    .await?
+    .into_iter()
+    .filter(|alias| source::key(&alias.target) == key)
+    .collect();
+
+    Ok(aliases)
+}
+
+/// Aliases whose target is the given link's source
+pub async fn list_dependent_aliases(
+    app_state: &AppState,
+    get: &GetLink,
+) -> Result<Vec<Link>, Error> {
+    println!("💽 List aliases of '{}'", get.id);
+    let link = get_link(app_state, get).await?;
+    let mut aliases = dependent_aliases(app_state, &get.identity, &link).await?;
+    attach_details(app_state, &mut aliases).await?;
+
+    Ok(aliases)
+}
+
+/// Change a link's source, keeping the old source working as an alias to the new one, and
+/// optionally retargeting aliases of the old source. Returns the renamed link and every alias
+/// created or rewritten.
+pub async fn rename_link(
+    app_state: &AppState,
+    identity: &Identity,
+    get: &GetLink,
+    rename: &RenameLink,
+) -> Result<(Link, Vec<Link>), Error> {
+    println!("💽 Rename '{}' to '{}'", get.id, rename.source);
+    let before = get_link(app_state, get).await?;
+    check_editable(app_state, identity, &before)?;
+    if before.source == rename.source {
+        return Ok((before, vec![]));
+    }
+    // Only the spelling changes, e.g. `Jira` to `jira`, so the old name still resolves here
+    let respelled = source::key(&before.source) == source::key(&rename.source);
+    if !respelled {
+        check_source(app_state, identity, &rename.source)?;
+    }
+    let dependents = if rename.rewrite_aliases && !respelled {
+        dependent_aliases(app_state, identity, &before).await?
+    } else {
+        vec![]
+    };
+
+    let mut tx = app_state.db.begin().await?;
+    if !respelled {
+        ReleaseSource {
+            source: rename.source.clone(),
+            personal_to: before.personal_to.clone(),
+        }
+        .as_query()
+        .execute(&mut *tx)
+        .await?;
+    }
+    let mut link = rename
+        .as_query(get.id, identity)
+        .fetch_one(&mut *tx)
+        .await?;
+    snapshot(RevisionAction::Update, Some(&before), Some(&link))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut aliases = vec![];
+    if !respelled {
+        let alias = CreateLink {
+            source: before.source.clone(),
+            is_alias: true,
+            target: link.source.clone(),
+            description: format!("Renamed to {}", link.source),
+            valid_from: None,
+            valid_until: None,
+            personal: before.personal_to.is_some(),
+            is_collection: false,
+            redirect_status: None,
+            forwarding: Forwarding::Off,
+        }
+        .as_query(identity)
+        .fetch_one(&mut *tx)
+        .await?;
+        snapshot(RevisionAction::Create, None, Some(&alias))?
+            .as_query(identity)
+            .execute(&mut *tx)
+            .await?;
+        aliases.push(alias);
+    }
+
+    let retarget = RetargetAlias {
+        target: link.source.clone(),
+    };
+    for dependent in dependents {
+        let rewritten = retarget
+            .as_query(dependent.id, identity)
+            .fetch_one(&mut *tx)
+            .await?;
+        snapshot(RevisionAction::Update, Some(&dependent), Some(&rewritten))?
+            .as_query(identity)
+            .execute(&mut *tx)
+            .await?;
+        aliases.push(rewritten);
+    }
+    tx.commit().await?;
+
+    attach_details(app_state, std::slice::from_mut(&mut link)).await?;
+    attach_details(app_state, &mut aliases).await?;
+
+    Ok((link, aliases))
+}
+
+pub async fn deprecate_link(
+    app_state: &AppState,
+    identity: &Identity,
+    get: &GetLink,
+    deprecate: &DeprecateLink,
+) -> Result<Link, Error> {
+    println!(
+        "💽 Deprecate '{}' in favour of '{}'",
+        get.id,
+        deprecate.replaced_by.as_deref().unwrap_or_default()
+    );
+    let before = get_link(app_state, get).await?;
+    check_editable(app_state, identity, &before)?;
+    if let Some(replaced_by) = &deprecate.replaced_by {
+        let replacement = find_link(
+            app_state,
+            &FindLink {
+                source: replaced_by.clone(),
+                identity: identity.clone(),
+            },
+        )
+        .await?;
+        if replacement.is_none_or(|replacement| replacement.id == get.id) {
+            return Err(Error::RowNotFound);
+        }
+    }
+    let mut tx = app_state.db.begin().await?;
+    deprecate
+        .as_query(get.id, identity)
+        .execute(&mut *tx)
+        .await?;
+    let mut after = get.as_unscoped_query().fetch_one(&mut *tx).await?;
+    attach_details(app_state, std::slice::from_mut(&mut after)).await?;
+    snapshot(RevisionAction::Update, Some(&before), Some(&after))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(after)
+}
+
+pub async fn set_visibility(
+    app_state: &AppState,
+    identity: &Identity,
+    get: &GetLink,
+    set: &SetVisibility,
+) -> Result<Link, Error> {
+    println!("💽 Make '{}' {}", get.id, set.visibility);
+    let before = get_link(app_state, get).await?;
+    check_editable(app_state, identity, &before)?;
+    // Who may see a link is up to its owners, so that of links nobody owns is left to admins
+    if before.owner.is_none()
+        && before.personal_to.is_none()
+        && !app_state.source_policy.is_admin(identity)
+    {
+        return Err(forbidden(identity, &before));
+    }
+    let mut tx = app_state.db.begin().await?;
+    set.as_query(get.id, identity).execute(&mut *tx).await?;
+    ClearLinkGroups { link_id: get.id }
+        .as_query()
+        .execute(&mut *tx)
+        .await?;
+    let groups = if set.visibility == Visibility::Restricted {
+        set.groups.clone()
+    } else {
+        vec![]
+    };
+    for name in &groups {
+        AddLinkGroup {
+            link_id: get.id,
+            name: name.clone(),
+        }
+        .as_query()
+        .execute(&mut *tx)
+        .await?;
+    }
+    let mut after = get.as_unscoped_query().fetch_one(&mut *tx).await?;
+    attach_details(app_state, std::slice::from_mut(&mut after)).await?;
+    // Read outside the transaction, so still the old groups
+    after.groups = groups;
+    snapshot(RevisionAction::Update, Some(&before), Some(&after))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(after)
+}
+
+pub async fn undeprecate_link(
+    app_state: &AppState,
+    identity: &Identity,
+    get: &GetLink,
+) -> Result<Link, Error> {
+    println!("💽 Undeprecate '{}'", get.id);
+    let before = get_link(app_state, get).await?;
+    check_editable(app_state, identity, &before)?;
+    let mut tx = app_state.db.begin().await?;
+    UndeprecateLink {}
+        .as_query(get.id, identity)
+        .execute(&mut *tx)
+        .await?;
+    let mut after = get.as_unscoped_query().fetch_one(&mut *tx).await?;
+    attach_details(app_state, std::slice::from_mut(&mut after)).await?;
+    snapshot(RevisionAction::Update, Some(&before), Some(&after))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(after)
+}
+
+pub async fn add_co_owner(
+    app_state: &AppState,
+    identity: &Identity,
+    add: &AddCoOwner,
+) -> Result<Link, Error> {
+    println!("💽 Add co-owner '{}' to '{}'", add.owner, add.link_id);
+    let get = GetLink {
+        id: add.link_id,
+        identity: identity.clone(),
+    };
+    let before = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &before)?;
+    let mut tx = app_state.db.begin().await?;
+    add.as_query().execute(&mut *tx).await?;
+    TouchLink { id: add.link_id }
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    let mut after = get.as_unscoped_query().fetch_one(&mut *tx).await?;
+    attach_details(app_state, std::slice::from_mut(&mut after)).await?;
+    // Read outside the transaction, so still the old co-owners
+    if !after.co_owners.contains(&add.owner) {
+        after.co_owners.push(add.owner.clone());
+    }
+    snapshot(RevisionAction::Update, Some(&before), Some(&after))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(after)
+}
+
+pub async fn add_schedule(
+    app_state: &AppState,
+    identity: &Identity,
+    add: &AddSchedule,
+) -> Result<Link, Error> {
+    println!(
+        "💽 Schedule '{}' for '{}' from '{}'",
+        add.target, add.link_id, add.active_from
+    );
+    let get = GetLink {
+        id: add.link_id,
+        identity: identity.clone(),
+    };
+    let link = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &link)?;
+    AddSchedule {
+        link_id: add.link_id,
+        target: normalize_target(app_state, link.is_alias, &add.target)?,
+        active_from: add.active_from,
+    }
+    .as_query()
+    .execute(&app_state.db)
+    .await?;
+
+    get_link(app_state, &get).await
+}
+
+/// Split a share of a link's traffic off to another target
+pub async fn add_variant(
+    app_state: &AppState,
+    identity: &Identity,
+    add: &AddVariant,
+) -> Result<Link, Error> {
+    println!(
+        "💽 Split {}% of '{}' to '{}'",
+        add.weight, add.link_id, add.target
+    );
+    let get = GetLink {
+        id: add.link_id,
+        identity: identity.clone(),
+    };
+    let link = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &link)?;
+    if link.is_alias || link.is_collection {
+        return Err(Error::Encode(
+            "only links with a URL target can be split".into(),
+        ));
+    }
+    let available = link.primary_weight();
+    if add.weight < 1 || add.weight > available {
+        return Err(Error::Encode(Box::new(TargetError::Weight {
+            weight: add.weight,
+            available,
+        })));
+    }
+    AddVariant {
+        link_id: add.link_id,
+        target: normalize_target(app_state, false, &add.target)?,
+        weight: add.weight,
+    }
+    .as_query()
+    .execute(&app_state.db)
+    .await?;
+
+    get_link(app_state, &get).await
+}
+
+pub async fn remove_variant(
+    app_state: &AppState,
+    identity: &Identity,
+    remove: &RemoveVariant,
+) -> Result<Link, Error> {
+    println!("💽 Unsplit '{}' from '{}'", remove.id, remove.link_id);
+    let get = GetLink {
+        id: remove.link_id,
+        identity: identity.clone(),
+    };
+    let link = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &link)?;
+    remove.as_query().execute(&app_state.db).await?;
+
+    get_link(app_state, &get).await
+}
+
+pub async fn remove_schedule(
+    app_state: &AppState,
+    identity: &Identity,
+    remove: &RemoveSchedule,
+) -> Result<Link, Error> {
+    println!("💽 Unschedule '{}' from '{}'", remove.id, remove.link_id);
+    let get = GetLink {
+        id: remove.link_id,
+        identity: identity.clone(),
+    };
+    let link = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &link)?;
+    remove.as_query().execute(&app_state.db).await?;
+
+    get_link(app_state, &get).await
+}
+
+/// Add an existing link to a collection, at the end
+pub async fn add_collection_item(
+    app_state: &AppState,
+    identity: &Identity,
+    get: &GetLink,
+    collect: &CollectLink,
+) -> Result<Link, Error> {
+    println!("💽 Collect '{}' into '{}'", collect.source, get.id);
+    let collection = get_link(app_state, get).await?;
+    check_editable(app_state, identity, &collection)?;
+    let find = FindLink {
+        source: collect.source.clone(),
+        identity: identity.clone(),
+    };
+    let member = find_link(app_state, &find)
+        .await?
+        .filter(|member| collection.is_collection && member.id != collection.id)
+        .ok_or(Error::RowNotFound)?;
+    AddCollectionItem {
+        collection_id: collection.id,
+        link_id: member.id,
+        note: collect.note.clone(),
+    }
+    .as_query()
+    .execute(&app_state.db)
+    .await?;
+
+    get_link(app_state, get).await
+}
+
+pub async fn update_collection_item(
+    app_state: &AppState,
+    identity: &Identity,
+    update: &UpdateCollectionItem,
+) -> Result<Link, Error> {
+    println!("💽 Annotate '{}' in '{}'", update.id, update.collection_id);
+    let get = GetLink {
+        id: update.collection_id,
+        identity: identity.clone(),
+    };
+    let link = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &link)?;
+    update.as_query().execute(&app_state.db).await?;
+
+    get_link(app_state, &get).await
+}
+
+pub async fn remove_collection_item(
+    app_state: &AppState,
+    identity: &Identity,
+    remove: &RemoveCollectionItem,
+) -> Result<Link, Error> {
+    println!(
+        "💽 Uncollect '{}' from '{}'",
+        remove.id, remove.collection_id
+    );
+    let get = GetLink {
+        id: remove.collection_id,
+        identity: identity.clone(),
+    };
+    let link = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &link)?;
+    remove.as_query().execute(&app_state.db).await?;
+
+    get_link(app_state, &get).await
+}
+
+pub async fn reorder_collection_items(
+    app_state: &AppState,
+    identity: &Identity,
+    reorder: &ReorderCollectionItems,
+) -> Result<Link, Error> {
+    println!(
+        "💽 Reorder {} items in '{}'",
+        reorder.item_ids.len(),
+        reorder.collection_id
+    );
+    let get = GetLink {
+        id: reorder.collection_id,
+        identity: identity.clone(),
+    };
+    let link = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &link)?;
+    reorder.as_query().execute(&app_state.db).await?;
+
+    get_link(app_state, &get).await
+}
+
+pub async fn remove_co_owner(
+    app_state: &AppState,
+    identity: &Identity,
+    remove: &RemoveCoOwner,
+) -> Result<Link, Error> {
+    println!(
+        "💽 Remove co-owner '{}' from '{}'",
+        remove.owner, remove.link_id
+    );
+    let get = GetLink {
+        id: remove.link_id,
+        identity: identity.clone(),
+    };
+    let before = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &before)?;
+    let mut tx = app_state.db.begin().await?;
+    remove.as_query().execute(&mut *tx).await?;
+    TouchLink { id: remove.link_id }
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    let mut after = get.as_unscoped_query().fetch_one(&mut *tx).await?;
+    attach_details(app_state, std::slice::from_mut(&mut after)).await?;
+    // Read outside the transaction, so still the old co-owners
+    after.co_owners.retain(|owner| owner != &remove.owner);
+    snapshot(RevisionAction::Update, Some(&before), Some(&after))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(after)
+}
+
+/// Move a link to the trash, from where it can be restored until it is purged
+pub async fn delete_link(
+    app_state: &AppState,
+    identity: &Identity,
+    delete: &DeleteLink,
+) -> Result<Link, Error> {
     println!("💽 Delete '{}'", delete.id);
+    let get = GetLink {
+        id: delete.id,
+        identity: identity.clone(),
+    };
+    let before = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &before)?;
+    let mut tx = app_state.db.begin().await?;
+    let query_result = delete.as_query(identity).execute(&mut *tx).await?;
+
+    if query_result.rows_affected() == 0 {
+        return Err(Error::RowNotFound);
+    }
+    snapshot(RevisionAction::Delete, Some(&before), None)?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let mut link = get.as_unscoped_query().fetch_one(&app_state.db).await?;
+    attach_details(app_state, std::slice::from_mut(&mut link)).await?;
+
+    Ok(link)
+}
+
+pub async fn restore_link(
+    app_state: &AppState,
+    identity: &Identity,
+    untrash: &UntrashLink,
+) -> Result<Link, Error> {
+    println!("💽 Restore '{}' from trash", untrash.id);
+    let mut tx = app_state.db.begin().await?;
+    let mut link = untrash.as_query().fetch_one(&mut *tx).await?;
+    attach_details(app_state, std::slice::from_mut(&mut link)).await?;
+    check_editable(app_state, identity, &link)?;
+    // Take the source back if it was released while in the trash and is still free
+    let reclaim = SetSourceKey {
+        id: link.id,
+        source_key: source::key(&link.source),
+    };
+    match reclaim.as_query().execute(&mut *tx).await {
+        Ok(_) => {}
+        Err(Error::Database(err)) if err.is_unique_violation() => {
+            println!(
+                "🔑 '{}' is taken, restoring as '{}~{}'",
+                link.source, reclaim.source_key, link.id
+            );
+        }
+        Err(err) => return Err(err),
+    }
+    snapshot(RevisionAction::Restore, None, Some(&link))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(link)
+}
+
+pub async fn list_trash(app_state: &AppState, identity: &Identity) -> Result<Vec<Link>, Error> {
+    println!("💽 List trash");
+    let list = ListTrash {
+        identity: identity.clone(),
+    };
+    let mut links = list.as_query().fetch_all(&app_state.db).await?;
+    attach_details(app_state, &mut links).await?;
+
+    Ok(links)
+}
+
+/// The user's personal links, none if they aren't authenticated
+pub async fn list_personal_links(
+    app_state: &AppState,
+    identity: &Identity,
+) -> Result<Vec<Link>, Error> {
+    let Some(user) = &identity.user else {
+        return Ok(vec![]);
+    };
+    println!("💽 List personal links of '{user}'");
+    let list = ListPersonalLinks { user: user.clone() };
+    let mut links = list.as_query().fetch_all(&app_state.db).await?;
+    attach_details(app_state, &mut links).await?;
+
+    Ok(links)
+}
+
+pub async fn list_namespaces(app_state: &AppState) -> Result<Vec<Namespace>, Error> {
+    println!("💽 List namespaces");
+    let keys = ListNamespaces {}
+        .as_query()
+        .fetch_all(&app_state.db)
+        .await?;
+
+    let mut counts = BTreeMap::<String, i64>::new();
+    for key in &keys {
+        let mut namespace = source::namespace(key);
+        while let Some(name) = namespace {
+            *counts.entry(name.to_string()).or_default() += 1;
+            namespace = source::namespace(name);
+        }
+    }
+
+    Ok(counts
+        .into_iter()
+        .map(|(name, links)| Namespace { name, links })
+        .collect())
+}
+
+pub async fn purge_trash(app_state: &AppState, purge: &PurgeTrash) -> Result<u64, Error> {
+    println!("💽 Purge trash older than {} days", purge.retention_days);
+    let query_result = purge.as_query().execute(&app_state.db).await?;
+
+    Ok(query_result.rows_affected())
+}
+
+/// Bring stored source keys in line with `source::key`, which the migration could only
+/// approximate, once for each `source::KEY_VERSION`
+///
+/// A key already claimed by another link falls back to `key~id` until the link is renamed
+pub async fn rekey_sources(app_state: &AppState) -> Result<u64, Error> {
+    let version = GetSourceKeyVersion {}
+        .as_query()
+        .fetch_one(&app_state.db)
+        .await?;
+    if version >= source::KEY_VERSION {
+        return Ok(0);
+    }
+    println!("💽 Rekey sources from version {version}");
+    let links = ListSourceKeys {}
+        .as_query()
+        .fetch_all(&app_state.db)
+        .await?;
+
+    let mut rekeyed = 0;
+    for (id, source, stored) in links {
+        let key = source::key(&source);
+        if stored == key || stored == format!("{key}~{id}") {
+            continue;
+        }
+        let rekey = SetSourceKey {
+            id,
+            source_key: key.clone(),
+        };
+        match rekey.as_query().execute(&app_state.db).await {
+            Ok(_) => {}
+            Err(Error::Database(err)) if err.is_unique_violation() => {
+                let rekey = SetSourceKey {
+                    id,
+                    source_key: format!("{key}~{id}"),
+                };
+                rekey.as_query().execute(&app_state.db).await?;
+            }
+            Err(err) => return Err(err),
+        }
+        rekeyed += 1;
+    }
+
+    SetSourceKeyVersion {
+        version: source::KEY_VERSION,
+    }
+    .as_query()
+    .execute(&app_state.db)
+    .await?;
+
+    Ok(rekeyed)
+}
+
+pub async fn purge_trash_job(app_state: Arc<AppState>, purge: PurgeTrash, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        match purge_trash(&app_state, &purge).await {
+            Ok(purged) => println!("🗑️ Purged {purged} links from the trash"),
+            Err(err) => eprintln!("🗑️ Failed to purge trash: {err}"),
+        }
+    }
+}
+
+pub async fn record_clicks_job(app_state: Arc<AppState>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(err) = flush_clicks(&app_state).await {
+            eprintln!("📈 Failed to record clicks: {err}");
+        }
+    }
+}
+
+/// Snapshot a link before and/or after a change
+fn snapshot(
+    action: RevisionAction,
+    before: Option<&Link>,
+    after: Option<&Link>,
+) -> Result<CreateRevision, Error> {
+    let to_value = |link: Option<&Link>| {
+        link.map(serde_json::to_value)
+            .transpose()
+            .map_err(|err| Error::Encode(Box::new(err)))
+    };
+    Ok(CreateRevision {
+        link_id: before.or(after).map(|link| link.id).unwrap_or_default(),
+        action,
+        before: to_value(before)?,
+        after: to_value(after)?,
+    })
+}
+
+pub async fn list_revisions(
+    app_state: &AppState,
+    list: &ListRevisions,
+) -> Result<Vec<Revision>, Error> {
+    println!("💽 List revisions of '{}'", list.link_id);
+    let revisions = list.as_query().fetch_all(&app_state.db).await?;
+
+    Ok(revisions)
+}
+
+/// Put a link's own fields back as of a revision, see `RestoreLink` for what that leaves out
+pub async fn restore_revision(
+    app_state: &AppState,
+    identity: &Identity,
+    get: &GetRevision,
+) -> Result<Link, Error> {
+    println!("💽 Restore revision '{}'", get.id);
+    let revision = get.as_query().fetch_one(&app_state.db).await?;
+    let Some(Json(state)) = revision.after.or(revision.before) else {
+        return Err(Error::RowNotFound);
+    };
+    let mut restore: RestoreLink =
+        serde_json::from_value(state).map_err(|err| Error::Decode(Box::new(err)))?;
+    restore.target = normalize_target(app_state, restore.is_alias, &restore.target)?;
+
+    // The revision is only found if the link is visible, but it may be in the trash
+    let mut before = GetLink {
+        id: revision.link_id,
+        identity: identity.clone(),
+    }
+    .as_unscoped_query()
+    .fetch_one(&app_state.db)
+    .await?;
+    attach_details(app_state, std::slice::from_mut(&mut before)).await?;
+    check_editable(app_state, identity, &before)?;
+    let rekeyed = source::key(&before.source) != source::key(&restore.source);
+    if rekeyed {
+        check_source(app_state, identity, &restore.source)?;
+    }
+
+    let mut tx = app_state.db.begin().await?;
+    if rekeyed {
+        ReleaseSource {
+            source: restore.source.clone(),
+            personal_to: before.personal_to.clone(),
+        }
+        .as_query()
+        .execute(&mut *tx)
+        .await?;
+    }
+    let mut link = restore
+        .as_query(revision.link_id, identity)
+        .fetch_one(&mut *tx)
+        .await?;
+    attach_details(app_state, std::slice::from_mut(&mut link)).await?;
+    snapshot(RevisionAction::Restore, Some(&before), Some(&link))?
+        .as_query(identity)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(link)
+}
+
+pub async fn cluster_topics(
+    app_state: &AppState,
+    cluster_topics: &ClusterTopics,
+) -> Result<Vec<Topic>, Error> {
+    let embeddings = ListEmbeddings {}
+        .as_query()
+        .fetch_all(&app_state.db)
+        .await?
+        .into_iter()
+        .map(|(id, blob)| Embedding::from_blob(id, &blob))
+        .collect::<Vec<_>>();
+    let k = cluster_topics
+        .k
+        .unwrap_or_else(|| cluster::default_k(embeddings.len()));
+    println!("💽 Cluster {} links into {} topics", embeddings.len(), k);
+
+    let clusters = tokio::task::spawn_blocking(move || cluster::kmeans(&embeddings, k))
+        .await
+        .map_err(|err| Error::Protocol(format!("Clustering topics failed: {err}")))?;
+
+    let mut tx = app_state.db.begin().await?;
+    ClearTopics {}.as_query().execute(&mut *tx).await?;
+    for cluster in clusters {
+        let mut sources = vec![];
+        for id in cluster.central() {
+            let link = GetLink {
+                id,
+                identity: Identity::default(),
+            }
+            .as_query()
+            .fetch_one(&mut *tx)
+            .await?;
+            sources.push(link.source);
+        }
+        let label = sources.join(", ");
+        let topic_id = CreateTopic { label }.as_query().fetch_one(&mut *tx).await?;
+        for member in cluster.members {
+            AssignTopic {
+                link_id: member.id,
+                topic_id,
+                distance: member.distance,
+            }
+            .as_query()
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    tx.commit().await?;
+
+    list_topics(app_state).await
+}
+
+pub async fn cluster_topics_job(
+    app_state: Arc<AppState>,
+    cluster_topics_opts: ClusterTopics,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        match cluster_topics(&app_state, &cluster_topics_opts).await {
+            Ok(topics) => println!("🗂️ Clustered links into {} topics", topics.len()),
+            Err(err) => eprintln!("🗂️ Failed to cluster links: {err}"),
+        }
+    }
+}
+
+pub async fn list_topics(app_state: &AppState) -> Result<Vec<Topic>, Error> {
+    println!("💽 List topics");
+    let topics = ListTopics {}.as_query().fetch_all(&app_state.db).await?;
+
+    Ok(topics)
+}
+
+pub async fn get_topic(app_state: &AppState, get: &GetTopic) -> Result<Topic, Error> {
+    println!("💽 Get topic '{}'", get.id);
+    let topic = get.as_query().fetch_one(&app_state.db).await?;
+
+    Ok(topic)
+}
+
+pub async fn query_topic_links(
+    app_state: &AppState,
+    query: &QueryTopicLinks,
+) -> Result<(Vec<Link>, u64), Error> {
+    println!(
+        "💽 Links in topic '{}', page '{}' size '{}'",
+        query.id, query.paging.page, query.paging.limit,
+    );
+    let mut links = query.as_query().fetch_all(&app_state.db).await?;
+    attach_details(app_state, &mut links).await?;
+    let count = query.as_count().fetch_one(&app_state.db).await?;
+    let last = (count as u64).div_ceil(query.paging.limit);
+
+    Ok((links, last))
+}
+
+pub async fn list_synonyms(app_state: &AppState) -> Result<Vec<Synonym>, Error> {
+    println!("💽 List synonyms");
+    let synonyms = ListSynonyms {}.as_query().fetch_all(&app_state.db).await?;
+
+    Ok(synonyms)
+}
+
+pub async fn get_synonym(app_state: &AppState, get: &GetSynonym) -> Result<Synonym, Error> {
+    println!("💽 Get synonym '{}'", get.id);
+    let synonym = get.as_query().fetch_one(&app_state.db).await?;
+
+    Ok(synonym)
+}
+
+pub async fn create_synonym(
+    app_state: &AppState,
+    create: &CreateSynonym,
+) -> Result<Synonym, Error> {
+    println!(
+        "💽 Create synonym '{}' <-> '{}'",
+        create.term, create.synonym
+    );
+    let synonym = create.as_query().fetch_one(&app_state.db).await?;
+
+    Ok(synonym)
+}
+
+pub async fn edit_synonym(
+    app_state: &AppState,
+    get: &GetSynonym,
+    update: &UpdateSynonym,
+) -> Result<Synonym, Error> {
+    println!("💽 Edit synonym '{}'", get.id);
+    get_synonym(app_state, get).await?;
+    let synonym = update.as_query(get.id).fetch_one(&app_state.db).await?;
+
+    Ok(synonym)
+}
+
+pub async fn delete_synonym(app_state: &AppState, delete: &DeleteSynonym) -> Result<(), Error> {
+    println!("💽 Delete synonym '{}'", delete.id);
+    let query_result = delete.as_query().execute(&app_state.db).await?;
+
+    if query_result.rows_affected() == 0 {
+        return Err(Error::RowNotFound);
+    }
+
+    Ok(())
+}
+
+/// Fill in the tags and co-owners of each link
+async fn attach_details(app_state: &AppState, links: &mut [Link]) -> Result<(), Error> {
+    let link_ids = links.iter().map(|link| link.id).collect::<Vec<_>>();
+    let link_tags = ListLinkTags {
+        link_ids: link_ids.clone(),
+    }
+    .as_query()
+    .fetch_all(&app_state.db)
+    .await?;
+    let co_owners = ListCoOwners {
+        link_ids: link_ids.clone(),
+    }
+    .as_query()
+    .fetch_all(&app_state.db)
+    .await?;
+    let schedules = ListSchedules {
+        link_ids: link_ids.clone(),
+    }
+    .as_query()
+    .fetch_all(&app_state.db)
+    .await?;
+    let variants = ListVariants {
+        link_ids: link_ids.clone(),
+    }
+    .as_query()
+    .fetch_all(&app_state.db)
+    .await?;
+    let groups = ListLinkGroups {
+        link_ids: link_ids.clone(),
+    }
+    .as_query()
+    .fetch_all(&app_state.db)
+    .await?;
+    let aliases = ListLinkAliases {}
+        .as_query()
+        .fetch_all(&app_state.db)
+        .await?
+        .into_iter()
+        .map(|(target, source)| (source::key(&target), source))
+        .collect::<Vec<_>>();
+    let items = ListCollectionItems {
+        link_ids: link_ids.clone(),
+    }
+    .as_query()
+    .fetch_all(&app_state.db)
+    .await?;
+    let usage = ListUsage { link_ids }
+        .as_query()
+        .fetch_all(&app_state.db)
+        .await?;
+
+    let for_link = |pairs: &[(i64, String)], id: i64| {
+        pairs
+            .iter()
+            .filter(|(link_id, _)| *link_id == id)
+            .map(|(_, value)| value.clone())
+            .collect()
+    };
+    for link in links.iter_mut() {
+        link.tags = for_link(&link_tags, link.id);
+        link.co_owners = for_link(&co_owners, link.id);
+        link.groups = for_link(&groups, link.id);
+        let key = source::key(&link.source);
+        link.aliases = aliases
+            .iter()
+            .filter(|(target, _)| *target == key)
+            .map(|(_, source)| source.clone())
+            .collect();
+        link.schedules = schedules
+            .iter()
+            .filter(|schedule| schedule.link_id == link.id)
+            .cloned()
+            .collect();
+        link.items = items
+            .iter()
+            .filter(|item| item.collection_id == link.id)
+            .cloned()
+            .collect();
+        link.variants = variants
+            .iter()
+            .filter(|variant| variant.link_id == link.id)
+            .cloned()
+            .collect();
+        link.usage = usage.iter().find(|usage| usage.link_id == link.id).cloned();
+    }
+
+    Ok(())
+}
+
+pub async fn list_tags(app_state: &AppState) -> Result<Vec<Tag>, Error> {
+    println!("💽 List tags");
+    let tags = ListTags {}.as_query().fetch_all(&app_state.db).await?;
+
+    Ok(tags)
+}
+
+pub async fn get_tag(app_state: &AppState, get: &GetTag) -> Result<Tag, Error> {
+    println!("💽 Get tag '{}'", get.id);
+    let tag = get.as_query().fetch_one(&app_state.db).await?;
+
+    Ok(tag)
+}
+
+pub async fn create_tag(app_state: &AppState, create: &CreateTag) -> Result<Tag, Error> {
+    println!("💽 Create tag '{}'", create.name);
+    check_tag(&create.name)?;
+    let tag = create.as_query().fetch_one(&app_state.db).await?;
+
+    Ok(tag)
+}
+
+pub async fn edit_tag(
+    app_state: &AppState,
+    get: &GetTag,
+    update: &UpdateTag,
+) -> Result<Tag, Error> {
+    println!("💽 Edit tag '{}'", get.id);
+    check_tag(&update.name)?;
+    get_tag(app_state, get).await?;
+    update.as_query(get.id).execute(&app_state.db).await?;
+
+    get_tag(app_state, get).await
+}
+
+pub async fn delete_tag(app_state: &AppState, delete: &DeleteTag) -> Result<(), Error> {
+    println!("💽 Delete tag '{}'", delete.id);
     let query_result = delete.as_query().execute(&app_state.db).await?;
 
     if query_result.rows_affected() == 0 {
@@ -105,3 +1501,166 @@ pub async fn delete_link(app_state: &AppState, delete: &DeleteLink) -> Result<()
 
     Ok(())
 }
+
+pub async fn tag_link(
+    app_state: &AppState,
+    identity: &Identity,
+    tag: &TagLink,
+) -> Result<Link, Error> {
+    println!("💽 Tag '{}' with '{}'", tag.link_id, tag.name);
+    check_tag(&tag.name)?;
+    let get = GetLink {
+        id: tag.link_id,
+        identity: identity.clone(),
+    };
+    let link = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &link)?;
+
+    let mut tx = app_state.db.begin().await?;
+    tag.as_tag_query().execute(&mut *tx).await?;
+    tag.as_query().execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    get_link(app_state, &get).await
+}
+
+pub async fn untag_link(
+    app_state: &AppState,
+    identity: &Identity,
+    untag: &UntagLink,
+) -> Result<Link, Error> {
+    println!("💽 Untag '{}' from '{}'", untag.link_id, untag.name);
+    let get = GetLink {
+        id: untag.link_id,
+        identity: identity.clone(),
+    };
+    let link = get_link(app_state, &get).await?;
+    check_editable(app_state, identity, &link)?;
+    untag.as_query().execute(&app_state.db).await?;
+
+    get_link(app_state, &get).await
+}
+
+pub async fn set_link_tags(
+    app_state: &AppState,
+    get: &GetLink,
+    set: &SetTags,
+) -> Result<Link, Error> {
+    println!("💽 Set tags of '{}'", get.id);
+    let names = set
+        .tags
+        .iter()
+        .filter(|name| !name.trim().is_empty())
+        .collect::<Vec<_>>();
+    for name in &names {
+        check_tag(name)?;
+    }
+    let link = get_link(app_state, get).await?;
+    check_editable(app_state, &get.identity, &link)?;
+
+    let mut tx = app_state.db.begin().await?;
+    ClearLinkTags { link_id: get.id }
+        .as_query()
+        .execute(&mut *tx)
+        .await?;
+    for name in names {
+        let tag = TagLink {
+            link_id: get.id,
+            name: name.clone(),
+        };
+        tag.as_tag_query().execute(&mut *tx).await?;
+        tag.as_query().execute(&mut *tx).await?;
+    }
+    tx.commit().await?;
+
+    get_link(app_state, get).await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_state;
+
+    fn create(source: &str, is_alias: bool, target: &str) -> CreateLink {
+        serde_json::from_value(json!({
+            "source": source,
+            "is_alias": is_alias,
+            "target": target,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn aliases_are_listed_whatever_spelling_they_target() {
+        let Some(app_state) = test_state().await else {
+            return;
+        };
+        let identity = Identity::default();
+        let link = create_link(
+            &app_state,
+            &identity,
+            &create("Team-Wiki", false, "https://wiki.example.com/"),
+        )
+        .await
+        .unwrap();
+        for (source, target) in [("kb", "team-wiki"), ("handbook", "TEAM_WIKI")] {
+            create_link(&app_state, &identity, &create(source, true, target))
+                .await
+                .unwrap();
+        }
+
+        let get = GetLink {
+            id: link.id,
+            identity,
+        };
+        let link = get_link(&app_state, &get).await.unwrap();
+        assert_eq!(link.aliases, vec!["handbook", "kb"]);
+    }
+
+    #[tokio::test]
+    async fn rename_changing_only_case_keeps_one_link() {
+        let Some(app_state) = test_state().await else {
+            return;
+        };
+        let identity = Identity::default();
+        let link = create_link(
+            &app_state,
+            &identity,
+            &create("Zebra-Tracker", false, "https://zebra.example.com/"),
+        )
+        .await
+        .unwrap();
+        create_link(
+            &app_state,
+            &identity,
+            &create("stripes", true, "Zebra-Tracker"),
+        )
+        .await
+        .unwrap();
+
+        let get = GetLink {
+            id: link.id,
+            identity: identity.clone(),
+        };
+        let rename = RenameLink {
+            source: "zebra-tracker".to_string(),
+            rewrite_aliases: true,
+        };
+        let (renamed, aliases) = rename_link(&app_state, &identity, &get, &rename)
+            .await
+            .unwrap();
+        assert_eq!(renamed.source, "zebra-tracker");
+        assert!(aliases.is_empty());
+
+        // The old spelling and the alias still lead to the same link
+        for source in ["Zebra-Tracker", "stripes"] {
+            let resolved = resolve_alias(app_state.clone(), &identity, source.to_string())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(resolved.id, link.id, "{source:?}");
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{identity::Identity, route::ROUTE_PREFIXES};
+
+/// Characters treated as the same word separator
+fn is_separator(c: char) -> bool {
+    c == '-' || c == '_' || c.is_whitespace()
+}
+
+/// Revision of `key`, bumped whenever it changes so that stored keys are recomputed once, on the
+/// next startup
+pub const KEY_VERSION: i64 = 1;
+
+/// The form of a source used for lookup and uniqueness, so that `Jira`, `jira` and `JIRA`, or
+/// `team-wiki`, `team_wiki` and `team wiki`, are the same link: NFKC-normalized, case-folded, with
+/// runs of `-`, `_` and whitespace collapsed to a single `-`
+pub fn key(source: &str) -> String {
+    let folded = source
+        .nfkc()
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .nfkc()
+        .collect::<String>();
+
+    let mut key = String::with_capacity(folded.len());
+    for word in folded.split(is_separator).filter(|word| !word.is_empty()) {
+        if !key.is_empty() {
+            key.push('-');
+        }
+        key.push_str(word);
+    }
+    key
+}
+
+/// The namespace a source lives in, e.g. `infra` for `infra/dashboards`
+pub fn namespace(source: &str) -> Option<&str> {
+    source
+        .rsplit_once('/')
+        .map(|(namespace, _)| namespace)
+        .filter(|namespace| !namespace.is_empty())
+}
+
+/// Why a source was refused by the naming policy
+#[derive(Debug, Serialize)]
+#[serde(tag = "violation", rename_all = "snake_case")]
+pub enum SourceError {
+    Length {
+        source: String,
+        min: usize,
+        max: usize,
+    },
+    Characters {
+        source: String,
+        invalid: Vec<char>,
+    },
+    Reserved {
+        source: String,
+        word: String,
+    },
+    Privileged {
+        source: String,
+        prefix: String,
+    },
+}
+impl Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Length { source, min, max } => {
+                write!(f, "'{source}' must be {min} to {max} characters long")
+            }
+            Self::Characters { source, invalid } => write!(
+                f,
+                "'{source}' may not contain {}",
+                invalid
+                    .iter()
+                    .map(|c| format!("'{c}'"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::Reserved { source, word } => write!(f, "'{source}' uses reserved word '{word}'"),
+            Self::Privileged { source, prefix } => {
+                write!(
+                    f,
+                    "'{source}' is under '{prefix}/', which needs elevated rights"
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for SourceError {}
+
+/// What a link may be called
+pub struct SourcePolicy {
+    /// Words that may not be used as a source, or as the first `/` segment of one
+    pub reserved: Vec<String>,
+    /// Characters allowed besides letters and digits
+    pub punctuation: String,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Namespaces that only `admins` may create links under
+    pub privileged: Vec<String>,
+    pub admins: Vec<String>,
+}
+impl Default for SourcePolicy {
+    fn default() -> Self {
+        Self {
+            reserved: ROUTE_PREFIXES.iter().map(|word| word.to_string()).collect(),
+            punctuation: "-_./".to_string(),
+            min_length: 1,
+            max_length: 64,
+            privileged: vec![],
+            admins: vec![],
+        }
+    }
+}
+impl SourcePolicy {
+    pub fn is_admin(&self, identity: &Identity) -> bool {
+        identity
+            .user
+            .as_ref()
+            .is_some_and(|user| self.admins.contains(user))
+    }
+
+    pub fn check(&self, source: &str, identity: &Identity) -> Result<(), SourceError> {
+        let length = source.trim().chars().count();
+        if length < self.min_length || length > self.max_length {
+            return Err(SourceError::Length {
+                source: source.to_string(),
+                min: self.min_length,
+                max: self.max_length,
+            });
+        }
+
+        // Each invalid character once, in the order they first appear
+        let mut invalid = vec![];
+        for c in source.trim().chars() {
+            if !c.is_alphanumeric() && !self.punctuation.contains(c) && !invalid.contains(&c) {
+                invalid.push(c);
+            }
+        }
+        if !invalid.is_empty() {
+            return Err(SourceError::Characters {
+                source: source.to_string(),
+                invalid,
+            });
+        }
+
+        let source_key = key(source);
+        let namespace = source_key.split('/').next().unwrap_or_default();
+        if let Some(word) = self.reserved.iter().find(|word| key(word) == namespace) {
+            return Err(SourceError::Reserved {
+                source: source.to_string(),
+                word: word.clone(),
+            });
+        }
+        if let Some(prefix) = self
+            .privileged
+            .iter()
+            .find(|prefix| key(prefix) == namespace)
+            && !self.is_admin(identity)
+        {
+            return Err(SourceError::Privileged {
+                source: source.to_string(),
+                prefix: prefix.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> SourcePolicy {
+        SourcePolicy {
+            reserved: vec!["admin".to_string()],
+            min_length: 2,
+            max_length: 20,
+            privileged: vec!["HR".to_string()],
+            admins: vec!["ada".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn user(name: &str) -> Identity {
+        Identity {
+            user: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn key_folds_case_and_separators() {
+        for source in [
+            "Team Wiki",
+            "team_wiki",
+            "TEAM-WIKI",
+            "team -_ wiki",
+            " team wiki-",
+        ] {
+            assert_eq!(key(source), "team-wiki", "{source:?}");
+        }
+    }
+
+    #[test]
+    fn key_normalizes_unicode() {
+        assert_eq!(key("ＷＩＫＩ"), "wiki");
+        assert_eq!(key("Café"), key("Cafe\u{301}"));
+        assert_eq!(key("Straße"), "straße");
+    }
+
+    #[test]
+    fn key_keeps_namespaces() {
+        assert_eq!(key("Infra/Dash Boards"), "infra/dash-boards");
+        assert_eq!(namespace("infra/dash-boards"), Some("infra"));
+        assert_eq!(namespace("a/b/c"), Some("a/b"));
+        assert_eq!(namespace("wiki"), None);
+        assert_eq!(namespace("/wiki"), None);
+    }
+
+    #[test]
+    fn accepts_ordinary_sources() {
+        let anonymous = Identity::default();
+        for source in ["wiki", "team-wiki", "infra/dash.v2", "administration"] {
+            assert!(policy().check(source, &anonymous).is_ok(), "{source:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_length() {
+        let anonymous = Identity::default();
+        for source in ["w", "  w  ", "abcdefghijklmnopqrstu"] {
+            assert!(
+                matches!(
+                    policy().check(source, &anonymous),
+                    Err(SourceError::Length {
+                        min: 2,
+                        max: 20,
+                        ..
+                    })
+                ),
+                "{source:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_characters() {
+        let Err(SourceError::Characters { invalid, .. }) =
+            policy().check("wi??ki#", &Identity::default())
+        else {
+            panic!("expected a character violation");
+        };
+        assert_eq!(invalid, vec!['?', '#']);
+    }
+
+    #[test]
+    fn reports_repeated_characters_once() {
+        let Err(SourceError::Characters { invalid, .. }) =
+            policy().check("w?i#k?i#", &Identity::default())
+        else {
+            panic!("expected a character violation");
+        };
+        assert_eq!(invalid, vec!['?', '#']);
+    }
+
+    #[test]
+    fn rejects_reserved_words_and_namespaces() {
+        let anonymous = Identity::default();
+        for source in ["admin", "Admin", "ADMIN/users"] {
+            assert!(
+                matches!(
+                    policy().check(source, &anonymous),
+                    Err(SourceError::Reserved { ref word, .. }) if word == "admin"
+                ),
+                "{source:?}"
+            );
+        }
+        // Reserved even for admins
+        assert!(policy().check("admin", &user("ada")).is_err());
+    }
+
+    #[test]
+    fn rejects_privileged_namespaces_unless_admin() {
+        for identity in [Identity::default(), user("bob")] {
+            assert!(matches!(
+                policy().check("hr/payroll", &identity),
+                Err(SourceError::Privileged { ref prefix, .. }) if prefix == "HR"
+            ));
+        }
+        assert!(policy().check("hr/payroll", &user("ada")).is_ok());
+        assert!(policy().check("hrm", &Identity::default()).is_ok());
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+/// Characters allowed in tag names besides letters and digits, none of which need quoting in a
+/// `tag:` filter or escaping in a `/go/tags/{tag}` path
+const PUNCTUATION: &str = "-_.";
+const MAX_LENGTH: usize = 32;
+
+/// Why a tag name was refused
+#[derive(Debug, Serialize)]
+#[serde(tag = "violation", rename_all = "snake_case")]
+pub enum TagError {
+    Length { name: String, max: usize },
+    Characters { name: String, invalid: Vec<char> },
+}
+impl Display for TagError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Length { name, max } => {
+                write!(f, "'{name}' must be 1 to {max} characters long")
+            }
+            Self::Characters { name, invalid } => write!(
+                f,
+                "'{name}' may not contain {}",
+                invalid
+                    .iter()
+                    .map(|c| format!("'{c}'"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+impl std::error::Error for TagError {}
+
+/// Check a tag name as it will be stored, trimmed and lowercased
+pub fn check(name: &str) -> Result<(), TagError> {
+    let length = name.trim().chars().count();
+    if length == 0 || length > MAX_LENGTH {
+        return Err(TagError::Length {
+            name: name.to_string(),
+            max: MAX_LENGTH,
+        });
+    }
+
+    // Each invalid character once, in the order they first appear
+    let mut invalid = vec![];
+    for c in name.trim().chars() {
+        if !c.is_alphanumeric() && !PUNCTUATION.contains(c) && !invalid.contains(&c) {
+            invalid.push(c);
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(TagError::Characters {
+            name: name.to_string(),
+            invalid,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_names() {
+        for name in ["infra", "On-Call", " team_a ", "v1.2", "größe"] {
+            assert!(check(name).is_ok(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_empty_and_long_names() {
+        for name in ["", "   ", &"x".repeat(MAX_LENGTH + 1)] {
+            assert!(
+                matches!(check(name), Err(TagError::Length { .. })),
+                "{name:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_quotes_and_spaces() {
+        let Err(TagError::Characters { invalid, .. }) = check(r#"say "hi" now"#) else {
+            panic!("expected a character violation");
+        };
+        assert_eq!(invalid, vec![' ', '"']);
+        assert!(check("a/b").is_err());
+        assert!(check("tag:x").is_err());
+    }
+}
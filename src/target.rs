@@ -0,0 +1,249 @@
+use std::fmt::Display;
+
+use url::{ParseError, Url};
+
+/// Query parameters that only track where a click came from
+const TRACKING_PARAMS: [&str; 6] = ["fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid"];
+
+pub fn is_tracking_param(name: &str) -> bool {
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name)
+}
+
+/// Lowercased host of a URL, e.g. `confluence.example.com`
+pub fn host(target: &str) -> Option<String> {
+    Url::parse(target.trim())
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+}
+
+/// A key under which URLs that lead to the same place compare equal, ignoring the http(s)
+/// scheme, host case, default ports, a trailing slash, the order of query parameters and
+/// tracking parameters
+pub fn canonical(target: &str) -> String {
+    let Ok(url) = Url::parse(target.trim()) else {
+        return target.trim().to_lowercase();
+    };
+    let scheme = match url.scheme() {
+        "http" | "https" => String::new(),
+        scheme => format!("{scheme}:"),
+    };
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    let port = url
+        .port()
+        .map(|port| format!(":{port}"))
+        .unwrap_or_default();
+    let path = url.path().trim_end_matches('/');
+
+    let mut params = url
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name))
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>();
+    params.sort();
+    let query = if params.is_empty() {
+        String::new()
+    } else {
+        format!("?{}", params.join("&"))
+    };
+    let fragment = url
+        .fragment()
+        .map(|fragment| format!("#{fragment}"))
+        .unwrap_or_default();
+
+    format!("{scheme}//{host}{port}{path}{query}{fragment}")
+}
+
+#[derive(Debug)]
+pub enum TargetError {
+    Unparseable {
+        target: String,
+        reason: ParseError,
+    },
+    Scheme {
+        scheme: String,
+        allowed: Vec<String>,
+    },
+    /// Variant weights would leave less than nothing for the link's own target
+    Weight {
+        weight: i64,
+        available: i64,
+    },
+}
+impl Display for TargetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unparseable { target, reason } => {
+                write!(f, "'{target}' is not a valid URL: {reason}")
+            }
+            Self::Scheme { scheme, allowed } => write!(
+                f,
+                "'{scheme}:' URLs are not allowed, use one of {}",
+                allowed.join(", ")
+            ),
+            Self::Weight { weight, available } => write!(
+                f,
+                "a variant can't take {weight}% of traffic, only 1% to {available}% is left"
+            ),
+        }
+    }
+}
+impl std::error::Error for TargetError {}
+
+/// What a link may point at, and how targets are tidied before they are stored
+pub struct TargetPolicy {
+    pub schemes: Vec<String>,
+    /// Drop tracking parameters such as `utm_source`
+    pub strip_tracking: bool,
+}
+impl Default for TargetPolicy {
+    fn default() -> Self {
+        Self {
+            schemes: vec!["http".to_string(), "https".to_string()],
+            strip_tracking: false,
+        }
+    }
+}
+impl TargetPolicy {
+    pub fn normalize(&self, target: &str) -> Result<String, TargetError> {
+        let mut url = Url::parse(target.trim()).map_err(|reason| TargetError::Unparseable {
+            target: target.to_string(),
+            reason,
+        })?;
+        if !self.schemes.iter().any(|scheme| scheme == url.scheme()) {
+            return Err(TargetError::Scheme {
+                scheme: url.scheme().to_string(),
+                allowed: self.schemes.clone(),
+            });
+        }
+
+        if self.strip_tracking && url.query_pairs().any(|(name, _)| is_tracking_param(&name)) {
+            let params = url
+                .query_pairs()
+                .filter(|(name, _)| !is_tracking_param(name))
+                .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                .collect::<Vec<_>>();
+            if params.is_empty() {
+                url.set_query(None);
+            } else {
+                url.query_pairs_mut().clear().extend_pairs(params);
+            }
+        }
+
+        Ok(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_ignores_cosmetic_differences() {
+        let canonical_url = canonical("https://wiki.example.com/space?a=1&b=2");
+        for url in [
+            "http://wiki.example.com/space?a=1&b=2",
+            "HTTPS://Wiki.Example.COM/space/?b=2&a=1",
+            "https://wiki.example.com:443/space?a=1&b=2",
+            "https://wiki.example.com/space?utm_source=mail&a=1&b=2&fbclid=x",
+            " https://wiki.example.com/space?a=1&b=2 ",
+        ] {
+            assert_eq!(canonical(url), canonical_url, "{url:?}");
+        }
+    }
+
+    #[test]
+    fn canonical_keeps_meaningful_differences() {
+        let canonical_url = canonical("https://wiki.example.com/space?a=1");
+        for url in [
+            "https://wiki.example.com/Space?a=1",
+            "https://wiki.example.com/space?a=2",
+            "https://wiki.example.com:8443/space?a=1",
+            "https://wiki.example.com/space?a=1#setup",
+            "ftp://wiki.example.com/space?a=1",
+        ] {
+            assert_ne!(canonical(url), canonical_url, "{url:?}");
+        }
+    }
+
+    fn stripping() -> TargetPolicy {
+        TargetPolicy {
+            strip_tracking: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalize_rejects_disallowed_schemes() {
+        for (target, scheme) in [
+            ("javascript:alert(document.cookie)", "javascript"),
+            ("JavaScript:alert(1)", "javascript"),
+            ("data:text/html;base64,PHNjcmlwdD4=", "data"),
+            ("file:///etc/passwd", "file"),
+        ] {
+            let Err(TargetError::Scheme {
+                scheme: refused, ..
+            }) = TargetPolicy::default().normalize(target)
+            else {
+                panic!("expected {target:?} to be refused");
+            };
+            assert_eq!(refused, scheme);
+        }
+    }
+
+    #[test]
+    fn normalize_rejects_unparseable_targets() {
+        for target in ["", "wiki.example.com/space", "https://"] {
+            assert!(
+                matches!(
+                    TargetPolicy::default().normalize(target),
+                    Err(TargetError::Unparseable { .. })
+                ),
+                "{target:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_lowercases_scheme_and_host() {
+        assert_eq!(
+            TargetPolicy::default()
+                .normalize(" HTTPS://Wiki.Example.COM/Space?Q=A ")
+                .unwrap(),
+            "https://wiki.example.com/Space?Q=A"
+        );
+        let mailto = TargetPolicy {
+            schemes: vec!["mailto".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            mailto.normalize("MailTo:team@example.com").unwrap(),
+            "mailto:team@example.com"
+        );
+    }
+
+    #[test]
+    fn normalize_strips_tracking_only_when_asked() {
+        let target = "https://example.com/page?utm_source=mail&id=7&gclid=abc#top";
+        assert_eq!(TargetPolicy::default().normalize(target).unwrap(), target);
+        assert_eq!(
+            stripping().normalize(target).unwrap(),
+            "https://example.com/page?id=7#top"
+        );
+        assert_eq!(
+            stripping()
+                .normalize("https://example.com/page?utm_campaign=launch")
+                .unwrap(),
+            "https://example.com/page"
+        );
+    }
+
+    #[test]
+    fn normalize_leaves_untracked_queries_alone() {
+        let target = "https://example.com/search?q=a%20b&b=2&a=1";
+        assert_eq!(stripping().normalize(target).unwrap(), target);
+        assert_eq!(
+            stripping().normalize("https://example.com").unwrap(),
+            "https://example.com/"
+        );
+    }
+}
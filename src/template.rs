@@ -1,8 +1,11 @@
 use askama::Template;
 
 use crate::{
-    model::{Link, Paging},
-    schema::{CreateLink, PagingOptions, SearchOptions},
+    model::{
+        Forwarding, Link, Namespace, Paging, REDIRECT_STATUSES, Revision, Stats, Synonym, Topic,
+        Visibility,
+    },
+    schema::{CreateLink, PagingOptions, SearchOptions, SortOptions},
 };
 
 #[allow(dead_code)]
@@ -22,12 +25,83 @@ pub struct ViewTemplate {
 #[template(path = "links/edit.html")]
 pub struct EditTemplate {
     pub link: Link,
+    /// Aliases targeting this link, which a rename can rewrite
+    pub aliases: Vec<Link>,
+}
+
+#[derive(Template)]
+#[template(path = "links/tags_edit.html")]
+pub struct TagsEditTemplate {
+    pub link: Link,
+}
+
+#[derive(Template)]
+#[template(path = "revisions/list.html")]
+pub struct RevisionsTemplate {
+    pub link_id: i64,
+    pub revisions: Vec<Revision>,
+}
+
+#[derive(Template)]
+#[template(path = "links/deleted.html")]
+pub struct DeletedTemplate {
+    pub link: Link,
+}
+
+#[derive(Template)]
+#[template(path = "trash/list.html")]
+pub struct TrashTemplate {
+    pub links: Vec<Link>,
+}
+
+#[derive(Template)]
+#[template(path = "namespaces/list.html")]
+pub struct NamespacesTemplate {
+    pub namespaces: Vec<Namespace>,
+}
+
+#[derive(Template)]
+#[template(path = "pages/namespaces.html")]
+pub struct NamespacesPageTemplate {
+    pub search: SearchOptions,
+}
+
+#[derive(Template)]
+#[template(path = "pages/trash.html")]
+pub struct TrashPageTemplate {
+    pub search: SearchOptions,
+}
+
+#[derive(Template)]
+#[template(path = "links/schedules_edit.html")]
+pub struct SchedulesEditTemplate {
+    pub link: Link,
+}
+
+#[derive(Template)]
+#[template(path = "links/variants_edit.html")]
+pub struct VariantsEditTemplate {
+    pub link: Link,
+}
+
+#[derive(Template)]
+#[template(path = "links/collection_edit.html")]
+pub struct CollectionEditTemplate {
+    pub link: Link,
+}
+
+#[derive(Template)]
+#[template(path = "links/by_target.html")]
+pub struct ByTargetTemplate {
+    pub links: Vec<Link>,
 }
 
 #[derive(Template)]
 #[template(path = "links/list.html")]
 pub struct ListTemplate {
     pub new: Option<CreateLink>,
+    /// The user's personal links, shown above the results
+    pub mine: Vec<Link>,
     pub links: Vec<Link>,
     pub paging: Paging,
 }
@@ -44,6 +118,94 @@ pub struct SearchTemplate {
 pub struct LinksTemplate {
     pub paging: PagingOptions,
     pub search: SearchOptions,
+    pub sort: SortOptions,
+}
+
+#[derive(Template)]
+#[template(path = "topics/list.html")]
+pub struct TopicsTemplate {
+    pub topics: Vec<Topic>,
+}
+
+#[derive(Template)]
+#[template(path = "pages/topics.html")]
+pub struct TopicsPageTemplate {
+    pub search: SearchOptions,
+}
+
+#[derive(Template)]
+#[template(path = "synonyms/view.html")]
+pub struct SynonymViewTemplate {
+    pub synonym: Synonym,
+}
+
+#[derive(Template)]
+#[template(path = "synonyms/edit.html")]
+pub struct SynonymEditTemplate {
+    pub synonym: Synonym,
+}
+
+#[derive(Template)]
+#[template(path = "synonyms/list.html")]
+pub struct SynonymsTemplate {
+    pub synonyms: Vec<Synonym>,
+}
+
+#[derive(Template)]
+#[template(path = "pages/synonyms.html")]
+pub struct SynonymsPageTemplate {
+    pub search: SearchOptions,
+}
+
+#[derive(Template)]
+#[template(path = "pages/expired.html")]
+pub struct ExpiredTemplate {
+    pub link: Link,
+    pub search: SearchOptions,
+}
+
+#[derive(Template)]
+#[template(path = "pages/preview.html")]
+pub struct PreviewTemplate {
+    pub link: Link,
+    /// Aliases passed through on the way to the link
+    pub chain: Vec<Link>,
+    pub stats: Stats,
+    pub search: SearchOptions,
+}
+
+#[derive(Template)]
+#[template(path = "pages/collection.html")]
+pub struct CollectionTemplate {
+    pub link: Link,
+    pub search: SearchOptions,
+}
+
+#[derive(Template)]
+#[template(path = "pages/deprecated.html")]
+pub struct DeprecatedTemplate {
+    pub link: Link,
+    pub search: SearchOptions,
+    /// Seconds before forwarding
+    pub countdown: u64,
+}
+
+#[derive(Template)]
+#[template(path = "links/deprecation_edit.html")]
+pub struct DeprecationEditTemplate {
+    pub link: Link,
+}
+
+#[derive(Template)]
+#[template(path = "links/visibility_edit.html")]
+pub struct VisibilityEditTemplate {
+    pub link: Link,
+}
+
+#[derive(Template)]
+#[template(path = "utils/alert.html")]
+pub struct AlertTemplate {
+    pub message: String,
 }
 
 #[derive(Template)]